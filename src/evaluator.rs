@@ -1,19 +1,45 @@
+pub mod context;
 pub mod error;
 pub mod expressions;
+pub mod macros;
+pub mod modules;
 pub mod object;
+#[cfg(feature = "regex")]
+pub mod patterns;
+pub mod prelude;
 pub mod statements;
 #[cfg(test)]
 mod test;
 
+use context::{Context, Frame};
 use object::environment::HeapEnvironment;
-use object::Object;
+use object::{FunctionObject, Object};
 
 pub trait Evaluate {
-    fn eval(&self, env: HeapEnvironment) -> error::Result<Object>;
-    fn eval_return(&self, env: HeapEnvironment) -> error::Result<Object> {
-        match self.eval(env) {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> error::Result<Object>;
+    fn eval_return(&self, env: HeapEnvironment, ctx: &mut Context) -> error::Result<Object> {
+        match self.eval(env, ctx) {
             Err(error::Error::Return(x)) => Ok(x),
             x => x,
         }
     }
 }
+
+/// Evaluation of nodes that can end a function body. Calls in tail position
+/// are handed back to the caller instead of being performed, so loops written
+/// as recursion don't nest Rust frames.
+pub trait EvaluateTail {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> error::Result<Tail>;
+}
+
+pub enum Tail {
+    Value(Object),
+    Call(TailCall),
+}
+
+/// A function call with its arguments already evaluated.
+pub struct TailCall {
+    pub function: FunctionObject,
+    pub arguments: Vec<Object>,
+    pub frame: Frame,
+}
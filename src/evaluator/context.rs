@@ -0,0 +1,402 @@
+use super::error::{Error, Result};
+use super::modules::{ModuleLoader, Modules};
+use super::object::builtins::Capability;
+use super::object::environment::HeapEnvironment;
+#[cfg(feature = "regex")]
+use super::patterns::Patterns;
+use crate::lexer::token::Location;
+use crate::parser::ast::expressions as expr;
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// State shared by every node during a single evaluation.
+pub struct Context {
+    frames: Vec<Frame>,
+    max_depth: usize,
+    steps: u64,
+    fuel: Option<u64>,
+    fuel_limit: Option<u64>,
+    deadline: Option<Instant>,
+    timeout: Option<Duration>,
+    max_allocation: Option<usize>,
+    cancellation: CancellationHandle,
+    capabilities: HashSet<Capability>,
+    random_state: u64,
+    stdout: Box<dyn Write>,
+    stderr: Box<dyn Write>,
+    stdin: Box<dyn Read>,
+    modules: Modules,
+    #[cfg(feature = "regex")]
+    patterns: Patterns,
+}
+
+impl fmt::Debug for Context {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Context")
+            .field("frames", &self.frames)
+            .field("max_depth", &self.max_depth)
+            .field("steps", &self.steps)
+            .field("fuel", &self.fuel)
+            .field("deadline", &self.deadline)
+            .field("max_allocation", &self.max_allocation)
+            .field("capabilities", &self.capabilities)
+            .field("modules", &self.modules)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Context {
+    fn default() -> Self {
+        Self {
+            frames: Vec::new(),
+            max_depth: Self::DEFAULT_MAX_DEPTH,
+            steps: 0,
+            fuel: None,
+            fuel_limit: None,
+            deadline: None,
+            timeout: None,
+            max_allocation: None,
+            cancellation: CancellationHandle::default(),
+            capabilities: HashSet::from(Capability::DEFAULT),
+            random_state: 0,
+            stdout: Box::new(io::stdout()),
+            stderr: Box::new(io::stderr()),
+            stdin: Box::new(io::stdin()),
+            modules: Modules::default(),
+            #[cfg(feature = "regex")]
+            patterns: Patterns::default(),
+        }
+    }
+}
+
+impl Context {
+    /// Every nested call uses a few KiB of native stack, this keeps the
+    /// default well within the 8 MiB main thread stack even in debug builds.
+    pub const DEFAULT_MAX_DEPTH: usize = 500;
+    const DEADLINE_INTERVAL: u64 = 64;
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits how many function calls can be nested before raising `StackOverflow`.
+    pub fn with_max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Limits how many expressions can be evaluated before raising `OutOfFuel`.
+    pub fn with_fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self.fuel_limit = Some(fuel);
+        self
+    }
+
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Timeouts too long to represent never expire.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self.deadline = Instant::now().checked_add(timeout);
+        self
+    }
+
+    /// Limits the length of arrays, hashes and strings built by scripts.
+    pub fn with_max_allocation(mut self, max_allocation: usize) -> Self {
+        self.max_allocation = Some(max_allocation);
+        self
+    }
+
+    /// Replaces the builtin groups scripts are allowed to use,
+    /// `Capability::DEFAULT` otherwise.
+    pub fn with_capabilities(mut self, capabilities: impl IntoIterator<Item = Capability>) -> Self {
+        self.capabilities = capabilities.into_iter().collect();
+        self
+    }
+
+    /// Redirects the output of `puts`.
+    pub fn with_stdout(mut self, stdout: impl Write + 'static) -> Self {
+        self.stdout = Box::new(stdout);
+        self
+    }
+
+    /// Redirects the output of `eputs`.
+    pub fn with_stderr(mut self, stderr: impl Write + 'static) -> Self {
+        self.stderr = Box::new(stderr);
+        self
+    }
+
+    /// Replaces the source `input` reads lines from.
+    pub fn with_stdin(mut self, stdin: impl Read + 'static) -> Self {
+        self.stdin = Box::new(stdin);
+        self
+    }
+
+    /// Replaces where `import` reads modules from, files by default.
+    pub fn with_loader(mut self, loader: impl ModuleLoader + 'static) -> Self {
+        self.modules.loader = Rc::new(loader);
+        self
+    }
+
+    /// Evaluates modules on top of `globals` instead of only the builtins.
+    pub fn with_module_globals(mut self, globals: HeapEnvironment) -> Self {
+        self.modules.globals = Some(globals);
+        self
+    }
+
+    pub(super) fn modules(&mut self) -> &mut Modules {
+        &mut self.modules
+    }
+
+    #[cfg(feature = "regex")]
+    pub(super) fn patterns(&mut self) -> &mut Patterns {
+        &mut self.patterns
+    }
+
+    pub fn stdout(&mut self) -> &mut dyn Write {
+        &mut self.stdout
+    }
+
+    pub fn stderr(&mut self) -> &mut dyn Write {
+        &mut self.stderr
+    }
+
+    /// Reads a line from the input without its line terminator, `None` once it is exhausted.
+    pub fn read_line(&mut self) -> io::Result<Option<String>> {
+        // Bytes are read one at a time so nothing past the line is consumed
+        let mut line = Vec::new();
+        let mut byte = [0];
+
+        loop {
+            match self.stdin.read(&mut byte) {
+                Ok(0) if line.is_empty() => return Ok(None),
+                Ok(0) => break,
+                Ok(_) if byte[0] == b'\n' => break,
+                Ok(_) => line.push(byte[0]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+
+        Ok(Some(String::from_utf8_lossy(&line).into_owned()))
+    }
+
+    pub fn allows(&self, capability: Capability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    /// Prepares the context for a new evaluation, refilling its fuel and
+    /// restarting its timeout. Cancellation is permanent.
+    pub fn reset(&mut self) {
+        self.frames.clear();
+        self.modules.reset();
+        self.steps = 0;
+        self.fuel = self.fuel_limit;
+
+        if let Some(timeout) = self.timeout {
+            self.deadline = Instant::now().checked_add(timeout);
+        }
+    }
+
+    /// A pseudo-random number from a xorshift generator seeded with the clock.
+    pub fn next_random(&mut self) -> u64 {
+        if self.random_state == 0 {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_nanos() as u64)
+                .unwrap_or_default();
+            self.random_state = seed | 1;
+        }
+
+        let mut x = self.random_state;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.random_state = x;
+
+        x
+    }
+
+    /// A handle that can stop this evaluation from another thread.
+    pub fn cancellation_handle(&self) -> CancellationHandle {
+        self.cancellation.clone()
+    }
+
+    pub fn fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// Accounts for a single evaluation step, failing once a limit is hit.
+    pub fn step(&mut self) -> Result<()> {
+        if let Some(fuel) = &mut self.fuel {
+            if *fuel == 0 {
+                return Err(Error::OutOfFuel);
+            }
+            *fuel -= 1;
+        }
+
+        if self.cancellation.is_cancelled() {
+            return Err(Error::Cancelled);
+        }
+
+        // Reading the clock on every step would dominate small expressions
+        if self.steps.is_multiple_of(Self::DEADLINE_INTERVAL) {
+            if let Some(deadline) = self.deadline {
+                if Instant::now() >= deadline {
+                    return Err(Error::Timeout);
+                }
+            }
+        }
+        self.steps += 1;
+
+        Ok(())
+    }
+
+    /// Checks a collection of `requested` elements may be allocated.
+    pub fn check_allocation(&self, requested: usize) -> Result<()> {
+        match self.max_allocation {
+            Some(limit) if requested > limit => Err(Error::AllocationError { requested, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.max_depth
+    }
+
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn push_frame(&mut self, frame: Frame) {
+        self.frames.push(frame);
+    }
+
+    pub fn pop_frame(&mut self) -> Option<Frame> {
+        self.frames.pop()
+    }
+
+    pub fn traceback(&self) -> Traceback {
+        Traceback(self.frames.clone())
+    }
+}
+
+/// An in-memory writer whose clones share the same buffer, useful to
+/// capture the output of a script after handing it to a `Context`.
+#[derive(Debug, Clone, Default)]
+pub struct OutputBuffer(Rc<RefCell<Vec<u8>>>);
+
+impl OutputBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+
+    pub fn clear(&self) {
+        self.0.borrow_mut().clear()
+    }
+}
+
+impl Write for OutputBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Cooperatively cancels the evaluation of the context it was taken from.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationHandle(Arc<AtomicBool>);
+
+impl CancellationHandle {
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// A function call currently being evaluated.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Frame {
+    /// Name the function was bound to with `let`, if any
+    pub function: Option<String>,
+    pub call: CallSite,
+    pub location: Location,
+}
+
+/// What made a call, only rendered when a traceback is displayed.
+#[derive(Debug, PartialEq, Clone)]
+pub enum CallSite {
+    /// A call expression in the source
+    Expression(Arc<expr::Call>),
+    /// A builtin calling back into the script
+    Callback(&'static str),
+}
+
+impl fmt::Display for CallSite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Expression(call) => write!(f, "{call}"),
+            Self::Callback(builtin) => write!(f, "<callback of {builtin}>"),
+        }
+    }
+}
+
+impl fmt::Display for Frame {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let function = self.function.as_deref().unwrap_or("<anonymous>");
+
+        write!(f, "at {function} ({}): {}", self.location, self.call)
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Traceback(pub Vec<Frame>);
+
+impl fmt::Display for Traceback {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Traceback (most recent call last):")?;
+
+        // Deep recursion is collapsed into a single line per repeated frame
+        let mut frames = self.0.iter().peekable();
+
+        while let Some(frame) = frames.next() {
+            writeln!(f, "  {frame}")?;
+
+            let mut repeated = 0;
+            while frames.next_if_eq(&frame).is_some() {
+                repeated += 1;
+            }
+
+            if repeated > 0 {
+                writeln!(f, "  [Previous frame repeated {repeated} more times]")?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -1,12 +1,19 @@
-use super::object::{Object, ObjectType};
+use super::context::Traceback;
+use super::object::builtins::Capability;
+use super::object::hash::{Hash, HashableObject};
+use super::object::{range::Range, Object, ObjectType};
+use crate::lexer::token::Location;
 use crate::parser::ast::expressions::{InfixOp, PrefixOp};
 
+use std::rc::Rc;
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum Error {
     #[error("Return statement returned {0}")]
     Return(Object),
+    #[error("Uncaught exception: {0}")]
+    Thrown(Object),
     #[error("Unknown operation: {operator}{type_value}")]
     PrefixError {
         operator: PrefixOp,
@@ -24,6 +31,8 @@ pub enum Error {
     CallableError(ObjectType),
     #[error("Expected {expected} arguments, {received} received")]
     ArgumentsError { expected: usize, received: usize },
+    #[error("Expected at least {minimum} arguments, {received} received")]
+    TooFewArgumentsError { minimum: usize, received: usize },
     #[error("Expected {expected} object, {received} received")]
     TypeError {
         expected: ObjectType,
@@ -31,10 +40,162 @@ pub enum Error {
     },
     #[error("Index '{0}' out of range")]
     IndexError(i64),
+    #[error("Range {0} is unbounded")]
+    RangeError(Range),
+    #[error("Maximum call depth of {0} exceeded")]
+    StackOverflow(usize),
+    #[error("Invalid value: {0}")]
+    ValueError(String),
+    #[error("Builtin {builtin} requires the {capability} capability")]
+    PermissionError {
+        builtin: String,
+        capability: Capability,
+    },
+    #[error("IO error: {0}")]
+    IOError(String),
+    #[error("Evaluation ran out of fuel")]
+    OutOfFuel,
+    #[error("Evaluation exceeded its deadline")]
+    Timeout,
+    #[error("Evaluation was cancelled")]
+    Cancelled,
+    #[error("Allocation of {requested} elements exceeds the limit of {limit}")]
+    AllocationError { requested: usize, limit: usize },
     #[error("Object of type {0} is unhashable")]
     HashError(ObjectType),
+    #[error("Couldn't import {path}: {reason}")]
+    ImportError { path: String, reason: String },
+    #[error("Import cycle: {}", .0.join(" -> "))]
+    ImportCycle(Vec<String>),
+    #[error("Invalid regular expression {pattern}: {reason}")]
+    RegexError { pattern: String, reason: String },
     #[error("Unknown evaluation error")]
     Unknown,
+    #[error("{1}{0}")]
+    Traced(Box<Error>, Traceback),
+}
+
+impl Error {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Return(_) => "Return",
+            Self::Thrown(_) => "Thrown",
+            Self::PrefixError { .. } => "PrefixError",
+            Self::InfixError { .. } => "InfixError",
+            Self::IdentifierError(_) => "IdentifierError",
+            Self::CallableError(_) => "CallableError",
+            Self::ArgumentsError { .. } | Self::TooFewArgumentsError { .. } => "ArgumentsError",
+            Self::TypeError { .. } => "TypeError",
+            Self::IndexError(_) => "IndexError",
+            Self::RangeError(_) => "RangeError",
+            Self::StackOverflow(_) => "StackOverflow",
+            Self::ValueError(_) => "ValueError",
+            Self::PermissionError { .. } => "PermissionError",
+            Self::IOError(_) => "IOError",
+            Self::OutOfFuel => "OutOfFuel",
+            Self::Timeout => "Timeout",
+            Self::Cancelled => "Cancelled",
+            Self::AllocationError { .. } => "AllocationError",
+            Self::HashError(_) => "HashError",
+            Self::ImportError { .. } => "ImportError",
+            Self::ImportCycle(_) => "ImportCycle",
+            Self::RegexError { .. } => "RegexError",
+            Self::Unknown => "Unknown",
+            Self::Traced(e, _) => e.kind(),
+        }
+    }
+
+    /// Whether a `try` block is allowed to recover from this error.
+    /// Hitting an evaluation limit always stops the script.
+    pub fn is_catchable(&self) -> bool {
+        !matches!(
+            self.root(),
+            Self::Return(_)
+                | Self::OutOfFuel
+                | Self::Timeout
+                | Self::Cancelled
+                | Self::AllocationError { .. }
+        )
+    }
+
+    /// The value bound by `catch`, thrown objects are passed through as is.
+    pub fn into_object(self) -> Object {
+        match self {
+            Self::Thrown(o) => o,
+            Self::Traced(e, traceback) => match e.into_object() {
+                Object::Hash(mut h) => {
+                    let hash = Rc::make_mut(&mut h);
+                    let frames = traceback.0.iter().map(|f| Object::Str(f.to_string()));
+
+                    hash.insert(
+                        HashableObject::Str("traceback".into()),
+                        Object::Array(frames.collect()),
+                    );
+                    // Where the innermost call was made
+                    if let Some(frame) = traceback.0.last() {
+                        hash.insert(
+                            HashableObject::Str("location".into()),
+                            location_object(frame.location),
+                        );
+                    }
+
+                    Object::Hash(h)
+                }
+                o => o,
+            },
+            e => error_object(e.kind(), &e.to_string()),
+        }
+    }
+
+    pub fn with_traceback(self, traceback: Traceback) -> Self {
+        match self {
+            Self::Return(_) => self,
+            Self::Traced(e, _) => Self::Traced(e, traceback),
+            e => Self::Traced(Box::new(e), traceback),
+        }
+    }
+
+    pub fn traceback(&self) -> Option<&Traceback> {
+        match self {
+            Self::Traced(_, traceback) => Some(traceback),
+            _ => None,
+        }
+    }
+
+    /// The error without its traceback.
+    pub fn root(&self) -> &Self {
+        match self {
+            Self::Traced(e, _) => e.root(),
+            e => e,
+        }
+    }
+}
+
+/// Builds the hash scripts use to represent errors.
+pub fn error_object(kind: &str, message: &str) -> Object {
+    let mut hash = Hash::new();
+
+    hash.insert(HashableObject::Str("kind".into()), Object::Str(kind.into()));
+    hash.insert(
+        HashableObject::Str("message".into()),
+        Object::Str(message.into()),
+    );
+
+    Object::Hash(hash.into())
+}
+
+/// Builds the hash scripts use to represent a source location.
+fn location_object(location: Location) -> Object {
+    let mut hash = Hash::new();
+
+    for (key, value) in [("line", location.line), ("column", location.column)] {
+        hash.insert(
+            HashableObject::Str(key.into()),
+            Object::Integer(value as i64),
+        );
+    }
+
+    Object::Hash(hash.into())
 }
 
 pub type Result<T, E = Error> = core::result::Result<T, E>;
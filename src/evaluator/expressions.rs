@@ -1,47 +1,75 @@
+use super::context::{CallSite, Context, Frame};
 use super::error::{Error, Result};
 use super::object::environment::{Environment, HeapEnvironment};
-use super::object::{hash, FunctionObject, Object, ObjectType};
-use super::Evaluate;
-use crate::parser::ast::expressions::{self as expr};
+use super::object::{hash, range, Array, FunctionObject, Object, ObjectType};
+use super::{macros, Evaluate, EvaluateTail, Tail, TailCall};
+use crate::lexer::token::Location;
+use crate::parser::ast::expressions::{self as expr, Binding};
 
-use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
 
 impl Evaluate for expr::Expression {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        ctx.step()?;
+
         match self {
-            Self::Integer(i) => i.eval(env),
-            Self::Boolean(b) => b.eval(env),
-            Self::Prefix(p) => p.eval(env),
-            Self::Infix(i) => i.eval(env),
-            Self::If(i) => i.eval(env),
-            Self::Identifier(i) => i.eval(env),
-            Self::Function(func) => func.eval(env),
-            Self::Call(c) => c.eval(env),
-            Self::Str(s) => s.eval(env),
-            Self::Array(a) => a.eval(env),
-            Self::Index(i) => i.eval(env),
-            Self::Block(b) => b.eval(env),
-            Self::Hash(h) => h.eval(env),
+            Self::Integer(i) => i.eval(env, ctx),
+            Self::Boolean(b) => b.eval(env, ctx),
+            Self::Prefix(p) => p.eval(env, ctx),
+            Self::Infix(i) => i.eval(env, ctx),
+            Self::If(i) => i.eval(env, ctx),
+            Self::Identifier(i) => i.eval(env, ctx),
+            Self::Function(func) => func.eval(env, ctx),
+            Self::Macro(m) => m.eval(env, ctx),
+            Self::Call(c) => c.eval(env, ctx),
+            Self::Str(s) => s.eval(env, ctx),
+            Self::Array(a) => a.eval(env, ctx),
+            Self::Index(i) => i.eval(env, ctx),
+            Self::Block(b) => b.eval(env, ctx),
+            Self::Hash(h) => h.eval(env, ctx),
+            Self::Range(r) => r.eval(env, ctx),
+            Self::Try(t) => t.eval(env, ctx),
             _ => Ok(Object::Null),
         }
     }
 }
 
+impl EvaluateTail for expr::Expression {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        match self {
+            Self::If(i) => {
+                ctx.step()?;
+                i.eval_tail(env, ctx)
+            }
+            Self::Block(b) => {
+                ctx.step()?;
+                b.eval_tail(env, ctx)
+            }
+            Self::Call(c) => {
+                ctx.step()?;
+                c.eval_tail(env, ctx)
+            }
+            e => e.eval(env, ctx).map(Tail::Value),
+        }
+    }
+}
+
 impl Evaluate for expr::Integer {
-    fn eval(&self, _: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, _: HeapEnvironment, _: &mut Context) -> Result<Object> {
         Ok(Object::Integer(self.value))
     }
 }
 
 impl Evaluate for expr::Boolean {
-    fn eval(&self, _: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, _: HeapEnvironment, _: &mut Context) -> Result<Object> {
         Ok(Object::Boolean(self.value))
     }
 }
 
 impl Evaluate for expr::Prefix {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let right = self.right.eval(env)?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let right = self.right.eval(env, ctx)?;
         Ok(match self.operator {
             expr::PrefixOp::Bang => match &right {
                 Object::Integer(0) => Object::Boolean(true),
@@ -69,13 +97,19 @@ impl Evaluate for expr::Prefix {
 }
 
 impl Evaluate for expr::Infix {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let left = self.left.eval(env.clone())?;
-        let right = self.right.eval(env)?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let left = self.left.eval(env.clone(), ctx)?;
+        let right = self.right.eval(env, ctx)?;
         match (left, right) {
             (Object::Integer(x), Object::Integer(y)) => evaluate_int_infix(&self.operator, x, y),
             (Object::Boolean(x), Object::Boolean(y)) => evaluate_bool_infix(&self.operator, x, y),
-            (Object::Str(x), Object::Str(y)) => evaluate_str_infix(&self.operator, &x, &y),
+            (Object::Str(x), Object::Str(y)) => {
+                if self.operator == expr::InfixOp::Plus {
+                    ctx.check_allocation(x.len() + y.len())?;
+                }
+
+                evaluate_str_infix(&self.operator, &x, &y)
+            }
             (x, y) => Err(Error::InfixError {
                 operator: self.operator.clone(),
                 type_left: (&x).into(),
@@ -134,21 +168,36 @@ fn evaluate_str_infix(op: &expr::InfixOp, x: &str, y: &str) -> Result<Object> {
 }
 
 impl Evaluate for expr::If {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let condition = self.condition.eval(env.clone())?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let condition = self.condition.eval(env.clone(), ctx)?;
 
         if is_truthy(condition) {
-            self.consequence.eval(env)
+            self.consequence.eval(env, ctx)
         } else {
             match &self.alternative {
-                Some(x) => x.eval(env),
+                Some(x) => x.eval(env, ctx),
                 None => Ok(Object::Null),
             }
         }
     }
 }
 
-fn is_truthy(x: Object) -> bool {
+impl EvaluateTail for expr::If {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        let condition = self.condition.eval(env.clone(), ctx)?;
+
+        if is_truthy(condition) {
+            self.consequence.eval_tail(env, ctx)
+        } else {
+            match &self.alternative {
+                Some(x) => x.eval_tail(env, ctx),
+                None => Ok(Tail::Value(Object::Null)),
+            }
+        }
+    }
+}
+
+pub(super) fn is_truthy(x: Object) -> bool {
     match x {
         Object::Boolean(true) => true,
         Object::Boolean(false) => false,
@@ -156,82 +205,229 @@ fn is_truthy(x: Object) -> bool {
         Object::Integer(_) => true,
         Object::Null => false,
         Object::Function(_) => true,
+        Object::Macro(_) => true,
+        Object::Quote(_) => true,
         Object::Builtin(_) => true,
+        Object::Native(_) => true,
         Object::Str(s) if s.is_empty() => false,
         Object::Str(_) => true,
         Object::Array(a) => !a.is_empty(),
         Object::Hash(h) => !h.is_empty(),
+        Object::Range(r) => !r.is_empty().unwrap_or(false),
     }
 }
 
 impl Evaluate for expr::Identifier {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        match env.borrow().get(&self.value) {
-            Some(o) => Ok(o),
-            None => Err(Error::IdentifierError(self.value.to_string())),
+    fn eval(&self, env: HeapEnvironment, _: &mut Context) -> Result<Object> {
+        let env = env.borrow();
+
+        let value = match self.binding {
+            Some(Binding::Local { depth, slot }) => env.get_slot(depth, slot),
+            Some(Binding::Global { depth }) => env.get_from(depth, &self.value),
+            None => env.get(&self.value),
+        };
+
+        value.ok_or_else(|| Error::IdentifierError(self.value.to_string()))
+    }
+}
+
+impl expr::Identifier {
+    /// Assigns `value` to this identifier in `env`.
+    pub(super) fn bind(&self, env: &HeapEnvironment, value: Object) {
+        match self.binding {
+            Some(Binding::Local { slot, .. }) => env.borrow_mut().set_slot(slot, value),
+            _ => {
+                env.borrow_mut().set(&self.value, value);
+            }
         }
     }
 }
 
 impl Evaluate for expr::Function {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, env: HeapEnvironment, _: &mut Context) -> Result<Object> {
         Ok(Object::Function(FunctionObject {
-            node: self.clone(),
+            node: Rc::new(self.clone()),
             env,
+            name: None,
         }))
     }
 }
 
-impl Evaluate for expr::Call {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let maybe_function = self.function.eval(env.clone())?;
+impl expr::Call {
+    fn eval_arguments(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Vec<Object>> {
+        let mut arguments = Vec::new();
+        for a in &self.arguments {
+            arguments.push(a.eval(env.clone(), ctx)?)
+        }
 
-        match &maybe_function {
-            Object::Builtin(b) => {
-                let mut arguments = Vec::new();
-                for a in &self.arguments {
-                    arguments.push(a.eval(env.clone())?)
-                }
+        Ok(arguments)
+    }
+}
 
-                b.call(arguments, env)
+impl EvaluateTail for Arc<expr::Call> {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        if self.is_quote() {
+            let expected = 1;
+            let received = self.arguments.len();
+            if received != expected {
+                return Err(Error::ArgumentsError { expected, received });
             }
+
+            return macros::quote(&self.arguments[0], env, ctx).map(Tail::Value);
+        }
+
+        let function = self.function.eval(env.clone(), ctx)?;
+
+        // Checked before the arguments are evaluated
+        match &function {
             Object::Function(f) => {
                 let received = self.arguments.len();
                 let expected = f.node.parameters.len();
                 if received != expected {
                     return Err(Error::ArgumentsError { expected, received });
                 }
+            }
+            Object::Builtin(_) | Object::Native(_) => {}
+            o => return Err(Error::CallableError(o.into())),
+        }
 
-                let mut arguments = Vec::new();
-                for a in &self.arguments {
-                    arguments.push(a.eval(env.clone())?)
-                }
+        let arguments = self.eval_arguments(env.clone(), ctx)?;
 
-                let parameters = f.node.parameters.iter().map(|p| p.value.as_ref());
+        apply(
+            function,
+            arguments,
+            self.location,
+            CallSite::Expression(self.clone()),
+            env,
+            ctx,
+        )
+    }
+}
+
+/// Calls `function` with `arguments` already evaluated, the way a call
+/// expression at `location` does. This is how builtins call back into
+/// scripts, `site` is the call shown in tracebacks.
+pub fn call(
+    function: Object,
+    arguments: Vec<Object>,
+    location: Location,
+    site: CallSite,
+    env: HeapEnvironment,
+    ctx: &mut Context,
+) -> Result<Object> {
+    match apply(function, arguments, location, site, env, ctx)? {
+        Tail::Value(o) => Ok(o),
+        Tail::Call(call) => call_function(call, ctx),
+    }
+}
+
+/// Builtins are called right away, user functions are returned to be called.
+fn apply(
+    function: Object,
+    arguments: Vec<Object>,
+    location: Location,
+    site: CallSite,
+    env: HeapEnvironment,
+    ctx: &mut Context,
+) -> Result<Tail> {
+    match function {
+        Object::Builtin(b) => b.call(arguments, location, env, ctx).map(Tail::Value),
+        Object::Native(n) => n.call(&arguments, ctx).map(Tail::Value),
+        Object::Function(f) => {
+            let received = arguments.len();
+            let expected = f.node.parameters.len();
+            if received != expected {
+                return Err(Error::ArgumentsError { expected, received });
+            }
+
+            Ok(Tail::Call(TailCall {
+                frame: Frame {
+                    function: f.name.clone(),
+                    call: site,
+                    location,
+                },
+                function: f,
+                arguments,
+            }))
+        }
+        o => Err(Error::CallableError((&o).into())),
+    }
+}
+
+impl Evaluate for Arc<expr::Call> {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        match self.eval_tail(env, ctx)? {
+            Tail::Value(o) => Ok(o),
+            Tail::Call(call) => call_function(call, ctx),
+        }
+    }
+}
 
-                for (param, arg) in parameters.zip(arguments) {
-                    f.env.borrow_mut().set(param, arg);
+/// Runs a function and every call it makes in tail position, reusing its
+/// frame. The entry frame is kept so tracebacks show where the chain started,
+/// only the latest of the tail calls after it is recorded.
+fn call_function(call: TailCall, ctx: &mut Context) -> Result<Object> {
+    if ctx.depth() >= ctx.max_depth() {
+        return Err(Error::StackOverflow(ctx.max_depth()));
+    }
+
+    let TailCall {
+        mut function,
+        mut arguments,
+        frame,
+    } = call;
+
+    ctx.push_frame(frame);
+    let entry_depth = ctx.depth();
+
+    let result = loop {
+        let call_env = Environment::new_heap(Some(function.env.clone()));
+
+        for (param, arg) in function.node.parameters.iter().zip(arguments) {
+            param.bind(&call_env, arg);
+        }
+
+        // Parameters and the body share a scope
+        match function.node.body.statements.eval_tail(call_env, ctx) {
+            Ok(Tail::Value(o)) | Err(Error::Return(o)) => break Ok(o),
+            Ok(Tail::Call(next)) => {
+                if ctx.depth() > entry_depth {
+                    ctx.pop_frame();
+                } else if ctx.depth() >= ctx.max_depth() {
+                    break Err(Error::StackOverflow(ctx.max_depth()));
                 }
 
-                Ok(f.node.body.eval_return(f.env.clone())?)
+                ctx.push_frame(next.frame);
+                function = next.function;
+                arguments = next.arguments;
             }
-            o => Err(Error::CallableError(o.into())),
+            // The innermost frame records the whole stack
+            Err(e) if e.traceback().is_none() => break Err(e.with_traceback(ctx.traceback())),
+            Err(e) => break Err(e),
         }
+    };
+
+    while ctx.depth() >= entry_depth {
+        ctx.pop_frame();
     }
+
+    result
 }
 
 impl Evaluate for expr::Str {
-    fn eval(&self, _: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, _: HeapEnvironment, _: &mut Context) -> Result<Object> {
         Ok(Object::Str(self.value.to_string()))
     }
 }
 
 impl Evaluate for expr::Array {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let mut objects = Vec::new();
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let mut objects = Array::new();
+
+        ctx.check_allocation(self.value.len())?;
 
         for elem in &self.value {
-            objects.push(elem.eval(env.clone())?);
+            objects.push_back(elem.eval(env.clone(), ctx)?);
         }
 
         Ok(Object::Array(objects))
@@ -239,41 +435,50 @@ impl Evaluate for expr::Array {
 }
 
 impl Evaluate for expr::Index {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let left = self.left.eval(env.clone())?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let left = self.left.eval(env.clone(), ctx)?;
 
         match &left {
-            Object::Array(v) => {
-                let index_object = self.index.eval(env)?;
+            Object::Array(v) => match self.index.eval(env, ctx)? {
+                Object::Integer(i) => Ok(v[range::checked_index(i, v.len())?].clone()),
+                Object::Range(r) => {
+                    let (start, end) = r.slice_bounds(v.len())?;
 
-                let index: i64 = match &index_object {
-                    Object::Integer(i) => *i,
-                    o => {
-                        return Err(Error::TypeError {
-                            expected: ObjectType::Integer,
-                            received: o.into(),
-                        })
-                    }
-                };
-
-                let index = if index < 0 {
-                    (v.len() as i64) + index
-                } else {
-                    index
-                };
+                    Ok(Object::Array(v.clone().slice(start..end)))
+                }
+                o => Err(Error::TypeError {
+                    expected: ObjectType::Integer,
+                    received: (&o).into(),
+                }),
+            },
+            // Strings are indexed by Unicode scalar values, not bytes
+            Object::Str(s) => match self.index.eval(env, ctx)? {
+                Object::Integer(i) => {
+                    let index = range::checked_index(i, s.chars().count())?;
 
-                let index: usize = match index.try_into() {
-                    Ok(v) => v,
-                    Err(_) => return Err(Error::IndexError(index)),
-                };
+                    Ok(Object::Str(s.chars().skip(index).take(1).collect()))
+                }
+                Object::Range(r) => {
+                    let (start, end) = r.slice_bounds(s.chars().count())?;
 
-                match v.get(index) {
-                    Some(o) => Ok(o.clone()),
-                    _ => Err(Error::IndexError(index as i64)),
+                    Ok(Object::Str(
+                        s.chars().skip(start).take(end - start).collect(),
+                    ))
                 }
-            }
+                o => Err(Error::TypeError {
+                    expected: ObjectType::Integer,
+                    received: (&o).into(),
+                }),
+            },
+            Object::Range(r) => match self.index.eval(env, ctx)? {
+                Object::Integer(i) => Ok(Object::Integer(r.get(i)?)),
+                o => Err(Error::TypeError {
+                    expected: ObjectType::Integer,
+                    received: (&o).into(),
+                }),
+            },
             Object::Hash(h) => {
-                let index_object = self.index.eval(env)?;
+                let index_object = self.index.eval(env, ctx)?;
                 let index = hash::HashableObject::try_from(&index_object)?;
 
                 match h.get(&index) {
@@ -291,26 +496,79 @@ impl Evaluate for expr::Index {
 }
 
 impl Evaluate for expr::StmtBlock {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
         let inner_env = Environment::new_heap(Some(env));
 
-        self.statements.eval(inner_env)
+        self.statements.eval(inner_env, ctx)
+    }
+}
+
+impl EvaluateTail for expr::StmtBlock {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        let inner_env = Environment::new_heap(Some(env));
+
+        self.statements.eval_tail(inner_env, ctx)
     }
 }
 
 impl Evaluate for expr::Hash {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let mut hash_map = HashMap::new();
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        ctx.check_allocation(self.entries.len())?;
+
+        let mut hash_map = hash::Hash::new();
 
         for (k, v) in &self.entries {
-            let k = k.eval(env.clone())?;
+            let k = k.eval(env.clone(), ctx)?;
             let k_hash = hash::HashableObject::try_from(&k)?;
 
-            let v = v.eval(env.clone())?;
+            let v = v.eval(env.clone(), ctx)?;
 
             hash_map.insert(k_hash, v);
         }
 
-        Ok(Object::Hash(hash_map))
+        Ok(Object::Hash(hash_map.into()))
+    }
+}
+
+impl Evaluate for expr::Range {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let mut bounds = [None, None];
+
+        for (bound, expression) in bounds.iter_mut().zip([&self.start, &self.end]) {
+            if let Some(e) = expression {
+                *bound = match e.eval(env.clone(), ctx)? {
+                    Object::Integer(i) => Some(i),
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Integer,
+                            received: (&o).into(),
+                        })
+                    }
+                };
+            }
+        }
+
+        let [start, end] = bounds;
+
+        Ok(Object::Range(range::Range {
+            start,
+            end,
+            inclusive: self.inclusive,
+        }))
+    }
+}
+
+impl Evaluate for expr::Try {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        match self.block.eval(env.clone(), ctx) {
+            Err(e) if e.is_catchable() => {
+                let handler_env = Environment::new_heap(Some(env));
+                self.error.bind(&handler_env, e.into_object());
+
+                // The error binding and the handler share a scope
+                self.handler.statements.eval(handler_env, ctx)
+            }
+            x => x,
+        }
     }
 }
@@ -0,0 +1,139 @@
+use super::context::Context;
+use super::error::{Error, Result};
+use super::object::environment::{Environment, HeapEnvironment};
+use super::object::{MacroObject, Object, ObjectType};
+use super::Evaluate;
+use crate::parser::ast::expressions::{self as expr, Expression};
+use crate::parser::ast::modify::Modify;
+use crate::parser::ast::statements as stmt;
+
+use std::rc::Rc;
+
+impl Evaluate for expr::Macro {
+    fn eval(&self, env: HeapEnvironment, _: &mut Context) -> Result<Object> {
+        Ok(Object::Macro(MacroObject {
+            node: Rc::new(self.clone()),
+            env,
+        }))
+    }
+}
+
+impl expr::Call {
+    /// Whether this is `quote(...)`, which receives its argument unevaluated.
+    pub fn is_quote(&self) -> bool {
+        self.is_call_to("quote")
+    }
+
+    pub fn is_unquote(&self) -> bool {
+        self.is_call_to("unquote")
+    }
+
+    fn is_call_to(&self, name: &str) -> bool {
+        matches!(self.function.as_ref(), Expression::Identifier(i) if &*i.value == name)
+    }
+}
+
+/// Wraps `node` in a quote, replacing each `unquote(...)` in it by the code
+/// for the value of its argument.
+pub(super) fn quote(node: &Expression, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+    let mut node = node.clone();
+
+    node.modify(&mut |e| {
+        let Expression::Call(call) = e else {
+            return Ok(());
+        };
+        if !call.is_unquote() {
+            return Ok(());
+        }
+
+        let expected = 1;
+        let received = call.arguments.len();
+        if received != expected {
+            return Err(Error::ArgumentsError { expected, received });
+        }
+
+        *e = into_code(call.arguments[0].eval(env.clone(), ctx)?)?;
+        Ok(())
+    })?;
+
+    Ok(Object::Quote(Rc::new(node)))
+}
+
+/// Code evaluating to `object`.
+fn into_code(object: Object) -> Result<Expression> {
+    match object {
+        Object::Integer(value) => Ok(Expression::Integer(expr::Integer { value })),
+        Object::Boolean(value) => Ok(Expression::Boolean(expr::Boolean { value })),
+        Object::Str(s) => Ok(Expression::Str(expr::Str { value: s.into() })),
+        Object::Quote(q) => Ok(Rc::unwrap_or_clone(q)),
+        o => Err(Error::TypeError {
+            expected: ObjectType::Quote,
+            received: (&o).into(),
+        }),
+    }
+}
+
+/// Removes every top level `let` binding a macro from `program`, defining the
+/// macros in `env`.
+pub fn define_macros(program: &mut Vec<stmt::Statement>, env: &HeapEnvironment) {
+    program.retain(|s| match s {
+        stmt::Statement::Let(stmt::Let {
+            name,
+            value: Expression::Macro(m),
+        }) => {
+            let definition = MacroObject {
+                node: Rc::new(m.clone()),
+                env: env.clone(),
+            };
+            env.borrow_mut().set(&name.value, Object::Macro(definition));
+
+            false
+        }
+        _ => true,
+    });
+}
+
+/// Replaces every call to a macro defined in `env` by the code it returns.
+pub fn expand_macros(
+    program: &mut Vec<stmt::Statement>,
+    env: &HeapEnvironment,
+    ctx: &mut Context,
+) -> Result<()> {
+    program.modify(&mut |e| {
+        let Expression::Call(call) = e else {
+            return Ok(());
+        };
+        let Expression::Identifier(name) = call.function.as_ref() else {
+            return Ok(());
+        };
+        let Some(Object::Macro(m)) = env.borrow().get(&name.value) else {
+            return Ok(());
+        };
+
+        *e = expand(&m, call, ctx)?;
+        Ok(())
+    })
+}
+
+fn expand(m: &MacroObject, call: &expr::Call, ctx: &mut Context) -> Result<Expression> {
+    let expected = m.node.parameters.len();
+    let received = call.arguments.len();
+    if received != expected {
+        return Err(Error::ArgumentsError { expected, received });
+    }
+
+    let macro_env = Environment::new_heap(Some(m.env.clone()));
+
+    for (parameter, argument) in m.node.parameters.iter().zip(&call.arguments) {
+        let argument = Object::Quote(Rc::new(argument.clone()));
+        macro_env.borrow_mut().set(&parameter.value, argument);
+    }
+
+    match m.node.body.statements.eval_return(macro_env, ctx)? {
+        Object::Quote(q) => Ok(Rc::unwrap_or_clone(q)),
+        o => Err(Error::TypeError {
+            expected: ObjectType::Quote,
+            received: (&o).into(),
+        }),
+    }
+}
@@ -0,0 +1,219 @@
+use super::context::Context;
+use super::error::{Error, Result};
+use super::macros;
+use super::object::builtins::Capability;
+use super::object::environment::{Environment, HeapEnvironment};
+use super::object::hash::{self, HashableObject};
+use super::object::Object;
+use super::Evaluate;
+use crate::parser::ast::statements as stmt;
+use crate::parser::Parse;
+use crate::resolver;
+
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::{fmt, fs};
+
+/// Finds and reads the source of imported modules.
+pub trait ModuleLoader {
+    /// Identifies the module `path` refers to when imported from the module
+    /// `importer`, or from the main script. Modules with the same id are only
+    /// evaluated once.
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String>;
+
+    fn load(&self, id: &str) -> io::Result<String>;
+
+    /// Capability scripts need to import through this loader.
+    fn capability(&self) -> Option<Capability> {
+        None
+    }
+}
+
+impl<L: ModuleLoader + ?Sized> ModuleLoader for Box<L> {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String> {
+        (**self).resolve(path, importer)
+    }
+
+    fn load(&self, id: &str) -> io::Result<String> {
+        (**self).load(id)
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        (**self).capability()
+    }
+}
+
+/// Loads modules from files, relative to the importing module or to `root`
+/// for the main script.
+#[derive(Debug, Default)]
+pub struct FileSystemLoader {
+    root: PathBuf,
+}
+
+impl FileSystemLoader {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl ModuleLoader for FileSystemLoader {
+    fn resolve(&self, path: &str, importer: Option<&str>) -> io::Result<String> {
+        let base = match importer.and_then(|i| Path::new(i).parent()) {
+            Some(directory) => directory,
+            None => &self.root,
+        };
+
+        let path = base.join(path).canonicalize()?;
+
+        Ok(path.to_string_lossy().into_owned())
+    }
+
+    fn load(&self, id: &str) -> io::Result<String> {
+        fs::read_to_string(id)
+    }
+
+    fn capability(&self) -> Option<Capability> {
+        Some(Capability::Fs)
+    }
+}
+
+/// Modules given by path, for embedders keeping scripts in memory.
+impl ModuleLoader for HashMap<String, String> {
+    fn resolve(&self, path: &str, _: Option<&str>) -> io::Result<String> {
+        match self.contains_key(path) {
+            true => Ok(path.to_string()),
+            false => Err(io::ErrorKind::NotFound.into()),
+        }
+    }
+
+    fn load(&self, id: &str) -> io::Result<String> {
+        self.get(id)
+            .cloned()
+            .ok_or_else(|| io::ErrorKind::NotFound.into())
+    }
+}
+
+/// Modules imported during the lifetime of a context.
+pub struct Modules {
+    pub(super) loader: Rc<dyn ModuleLoader>,
+    /// Environment every module is evaluated on top of
+    pub(super) globals: Option<HeapEnvironment>,
+    /// Exports of every module evaluated, by id
+    cache: HashMap<String, Object>,
+    /// Ids of the modules being evaluated, innermost last
+    loading: Vec<String>,
+}
+
+impl Default for Modules {
+    fn default() -> Self {
+        Self {
+            loader: Rc::new(FileSystemLoader::default()),
+            globals: None,
+            cache: HashMap::new(),
+            loading: Vec::new(),
+        }
+    }
+}
+
+impl fmt::Debug for Modules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Modules")
+            .field("cache", &self.cache.keys())
+            .field("loading", &self.loading)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Modules {
+    pub(super) fn reset(&mut self) {
+        self.loading.clear();
+    }
+}
+
+impl Evaluate for stmt::Import {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let module = import(&self.path, ctx)?;
+
+        self.name.bind(&env, module);
+        Ok(Object::Null)
+    }
+}
+
+/// The exports of the module at `path`, evaluating it the first time.
+fn import(path: &str, ctx: &mut Context) -> Result<Object> {
+    let loader = ctx.modules().loader.clone();
+
+    if let Some(capability) = loader.capability() {
+        if !ctx.allows(capability) {
+            return Err(Error::PermissionError {
+                builtin: "import".into(),
+                capability,
+            });
+        }
+    }
+
+    let import_error = |e: io::Error| Error::ImportError {
+        path: path.to_string(),
+        reason: e.to_string(),
+    };
+
+    let modules = ctx.modules();
+    let importer = modules.loading.last().map(String::as_str);
+    let id = loader.resolve(path, importer).map_err(import_error)?;
+
+    if let Some(module) = modules.cache.get(&id) {
+        return Ok(module.clone());
+    }
+    if let Some(start) = modules.loading.iter().position(|m| *m == id) {
+        let mut cycle = modules.loading[start..].to_vec();
+        cycle.push(id);
+
+        return Err(Error::ImportCycle(cycle));
+    }
+
+    let source = loader.load(&id).map_err(import_error)?;
+
+    ctx.modules().loading.push(id.clone());
+    let module = evaluate(&id, &source, ctx);
+    ctx.modules().loading.pop();
+
+    let module = module?;
+    ctx.modules().cache.insert(id, module.clone());
+
+    Ok(module)
+}
+
+/// Runs a module in its own environment, returning a hash of its exports.
+fn evaluate(id: &str, source: &str, ctx: &mut Context) -> Result<Object> {
+    let import_error = |reason: String| Error::ImportError {
+        path: id.to_string(),
+        reason,
+    };
+
+    let mut stmts: Vec<_> = source
+        .parser()
+        .collect::<Result<_, _>>()
+        .map_err(|e| import_error(format!("Parsing error: {e}")))?;
+
+    let env = Environment::new_heap(ctx.modules().globals.clone());
+
+    macros::define_macros(&mut stmts, &env);
+    macros::expand_macros(&mut stmts, &env, ctx)?;
+
+    resolver::resolve(&mut stmts, &env).map_err(|e| import_error(e.to_string()))?;
+
+    stmts.eval_return(env.clone(), ctx)?;
+
+    let mut exports = hash::Hash::new();
+
+    for s in &stmts {
+        if let stmt::Statement::Export(l) = s {
+            let value = env.borrow().get(&l.name.value).unwrap_or(Object::Null);
+            exports.insert(HashableObject::Str(l.name.value.to_string()), value);
+        }
+    }
+
+    Ok(Object::Hash(exports.into()))
+}
@@ -1,10 +1,26 @@
+#[cfg(test)]
+mod test;
+
 pub mod builtins;
+pub mod collector;
+pub mod convert;
 pub mod environment;
 pub mod hash;
+pub mod native;
+pub mod range;
+#[cfg(feature = "serde")]
+pub mod serialize;
 
-use crate::parser::ast::expressions::Function as AstFunction;
+use crate::parser::ast::expressions::{Expression, Function as AstFunction, Macro as AstMacro};
 
+use imbl::shared_ptr::RcK;
+use imbl::GenericVector;
 use std::fmt;
+use std::rc::Rc;
+
+/// A persistent vector, clones share their elements so they are O(1) and
+/// pushing to one only copies the chunk it modifies.
+pub type Array = GenericVector<Object, RcK>;
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Object {
@@ -12,10 +28,16 @@ pub enum Object {
     Boolean(bool),
     Null,
     Function(FunctionObject),
+    Macro(MacroObject),
+    /// Unevaluated code
+    Quote(Rc<Expression>),
     Str(String),
     Builtin(builtins::Builtin),
-    Array(Vec<Object>),
-    Hash(hash::Hash),
+    Native(native::Native),
+    Array(Array),
+    /// Shared between clones, copied on write
+    Hash(Rc<hash::Hash>),
+    Range(range::Range),
 }
 
 impl fmt::Display for Object {
@@ -25,8 +47,11 @@ impl fmt::Display for Object {
             Self::Boolean(b) => write!(f, "{b}"),
             Self::Null => write!(f, "null"),
             Self::Function(func) => write!(f, "{}", func.node),
+            Self::Macro(m) => write!(f, "{}", m.node),
+            Self::Quote(q) => write!(f, "QUOTE({q})"),
             Self::Str(s) => write!(f, "{s}"),
             Self::Builtin(b) => write!(f, "{b}"),
+            Self::Native(n) => write!(f, "{n}"),
             Self::Array(a) => {
                 let mut s = String::from("[");
                 let elems = a
@@ -55,6 +80,7 @@ impl fmt::Display for Object {
 
                 write!(f, "{s}")
             }
+            Self::Range(r) => write!(f, "{r}"),
         }
     }
 }
@@ -66,9 +92,12 @@ pub enum ObjectType {
     Null,
     Function,
     BuiltinFunction,
+    Macro,
+    Quote,
     Str,
     Array,
     Hash,
+    Range,
 }
 
 impl From<&Object> for ObjectType {
@@ -78,10 +107,13 @@ impl From<&Object> for ObjectType {
             Object::Boolean(_) => ObjectType::Boolean,
             Object::Null => ObjectType::Null,
             Object::Function(_) => ObjectType::Function,
+            Object::Macro(_) => ObjectType::Macro,
+            Object::Quote(_) => ObjectType::Quote,
             Object::Str(_) => ObjectType::Str,
-            Object::Builtin(_) => ObjectType::BuiltinFunction,
+            Object::Builtin(_) | Object::Native(_) => ObjectType::BuiltinFunction,
             Object::Array(_) => ObjectType::Array,
             Object::Hash(_) => ObjectType::Hash,
+            Object::Range(_) => ObjectType::Range,
         }
     }
 }
@@ -95,14 +127,26 @@ impl fmt::Display for ObjectType {
             ObjectType::Function => write!(f, "FUNCTION"),
             ObjectType::Str => write!(f, "STRING"),
             ObjectType::BuiltinFunction => write!(f, "BUILTIN FUNCTION"),
+            ObjectType::Macro => write!(f, "MACRO"),
+            ObjectType::Quote => write!(f, "QUOTE"),
             ObjectType::Array => write!(f, "ARRAY"),
             ObjectType::Hash => write!(f, "HASH"),
+            ObjectType::Range => write!(f, "RANGE"),
         }
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct FunctionObject {
-    pub node: AstFunction,
+    /// Shared so copies of the function don't clone its body
+    pub node: Rc<AstFunction>,
+    pub env: environment::HeapEnvironment,
+    /// Name of the first `let` the function was bound to
+    pub name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct MacroObject {
+    pub node: Rc<AstMacro>,
     pub env: environment::HeapEnvironment,
 }
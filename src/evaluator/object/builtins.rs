@@ -1,10 +1,20 @@
-use super::super::error::{Error, Result};
+use super::super::context::{CallSite, Context};
+use super::super::error::{error_object, Error, Result};
+use super::super::expressions::{call, is_truthy};
+#[cfg(feature = "regex")]
+use super::super::patterns::{match_object, CharIndexes};
 use super::environment::{Environment, HeapEnvironment};
-use super::{Object, ObjectType};
+use super::hash::{self, HashableObject};
+use super::{Array, Object, ObjectType};
+use crate::lexer::token::Location;
 
 use std::cell::RefCell;
+use std::cmp::Ordering;
 use std::fmt;
+use std::fs;
+use std::io::Write;
 use std::rc::Rc;
+use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, PartialEq, Clone)]
 pub enum Builtin {
@@ -13,22 +23,286 @@ pub enum Builtin {
     Rest,
     Push,
     Puts,
+    Eputs,
+    Input,
+    Error,
+    Time,
+    Random,
+    ReadFile,
+    WriteFile,
+    #[cfg(feature = "serde")]
+    JsonParse,
+    #[cfg(feature = "serde")]
+    JsonStringify,
+    Map,
+    Filter,
+    Reduce,
+    Sort,
+    SortBy,
+    Any,
+    All,
+    Find,
+    Zip,
+    Enumerate,
+    Split,
+    Join,
+    Trim,
+    Upper,
+    Lower,
+    Replace,
+    Contains,
+    StartsWith,
+    EndsWith,
+    IndexOf,
+    Repeat,
+    Chars,
+    PadLeft,
+    PadRight,
+    Format,
+    Keys,
+    Values,
+    Entries,
+    Has,
+    Merge,
+    Remove,
+    FromEntries,
+    Type,
+    Str,
+    Int,
+    Bool,
+    IsInt,
+    IsBool,
+    IsStr,
+    IsNull,
+    IsArray,
+    IsHash,
+    IsRange,
+    IsCallable,
+    #[cfg(feature = "regex")]
+    ReMatch,
+    #[cfg(feature = "regex")]
+    ReFindAll,
+    #[cfg(feature = "regex")]
+    ReReplace,
+    #[cfg(feature = "regex")]
+    ReSplit,
+}
+
+/// Groups of builtins a host can grant to or withhold from scripts. Only
+/// the `DEFAULT` groups, which scripts could always use, are granted unless
+/// the host allows more.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Capability {
+    Io,
+    EnvMutation,
+    Time,
+    Random,
+    Fs,
+}
+
+impl Capability {
+    /// Printing and deleting variables, which predate capabilities.
+    pub const DEFAULT: [Capability; 2] = [Self::Io, Self::EnvMutation];
+
+    pub const ALL: [Capability; 5] = [
+        Self::Io,
+        Self::EnvMutation,
+        Self::Time,
+        Self::Random,
+        Self::Fs,
+    ];
+}
+
+impl fmt::Display for Capability {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io => write!(f, "io"),
+            Self::EnvMutation => write!(f, "env-mutation"),
+            Self::Time => write!(f, "time"),
+            Self::Random => write!(f, "random"),
+            Self::Fs => write!(f, "fs"),
+        }
+    }
 }
 
 impl fmt::Display for Builtin {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
-            Self::Len => write!(f, "fn len(str_or_array) {{ BUILTIN }}"),
+            Self::Len => write!(f, "fn len(sequence) {{ BUILTIN }}"),
             Self::Del => write!(f, "fn del(str) {{ BUILTIN }}"),
-            Self::Rest => write!(f, "fn rest(array) {{ BUILTIN }}"),
+            Self::Rest => write!(f, "fn rest(sequence) {{ BUILTIN }}"),
             Self::Push => write!(f, "fn push(array) {{ BUILTIN }}"),
             Self::Puts => write!(f, "fn puts(...args) {{ BUILTIN }}"),
+            Self::Eputs => write!(f, "fn eputs(...args) {{ BUILTIN }}"),
+            Self::Input => write!(f, "fn input(prompt?) {{ BUILTIN }}"),
+            Self::Error => write!(f, "fn error(kind, message) {{ BUILTIN }}"),
+            Self::Time => write!(f, "fn time() {{ BUILTIN }}"),
+            Self::Random => write!(f, "fn random(max) {{ BUILTIN }}"),
+            Self::ReadFile => write!(f, "fn read_file(path) {{ BUILTIN }}"),
+            Self::WriteFile => write!(f, "fn write_file(path, contents) {{ BUILTIN }}"),
+            #[cfg(feature = "serde")]
+            Self::JsonParse => write!(f, "fn json_parse(str) {{ BUILTIN }}"),
+            #[cfg(feature = "serde")]
+            Self::JsonStringify => write!(f, "fn json_stringify(obj, indent?) {{ BUILTIN }}"),
+            Self::Map => write!(f, "fn map(collection, f) {{ BUILTIN }}"),
+            Self::Filter => write!(f, "fn filter(collection, predicate) {{ BUILTIN }}"),
+            Self::Reduce => write!(f, "fn reduce(collection, initial, f) {{ BUILTIN }}"),
+            Self::Sort => write!(f, "fn sort(sequence) {{ BUILTIN }}"),
+            Self::SortBy => write!(f, "fn sort_by(collection, key) {{ BUILTIN }}"),
+            Self::Any => write!(f, "fn any(collection, predicate) {{ BUILTIN }}"),
+            Self::All => write!(f, "fn all(collection, predicate) {{ BUILTIN }}"),
+            Self::Find => write!(f, "fn find(collection, predicate) {{ BUILTIN }}"),
+            Self::Zip => write!(f, "fn zip(left, right) {{ BUILTIN }}"),
+            Self::Enumerate => write!(f, "fn enumerate(sequence) {{ BUILTIN }}"),
+            Self::Split => write!(f, "fn split(str, separator?) {{ BUILTIN }}"),
+            Self::Join => write!(f, "fn join(array, separator) {{ BUILTIN }}"),
+            Self::Trim => write!(f, "fn trim(str) {{ BUILTIN }}"),
+            Self::Upper => write!(f, "fn upper(str) {{ BUILTIN }}"),
+            Self::Lower => write!(f, "fn lower(str) {{ BUILTIN }}"),
+            Self::Replace => write!(f, "fn replace(str, from, to) {{ BUILTIN }}"),
+            Self::Contains => write!(f, "fn contains(sequence, item) {{ BUILTIN }}"),
+            Self::StartsWith => write!(f, "fn starts_with(str, prefix) {{ BUILTIN }}"),
+            Self::EndsWith => write!(f, "fn ends_with(str, suffix) {{ BUILTIN }}"),
+            Self::IndexOf => write!(f, "fn index_of(str, substring) {{ BUILTIN }}"),
+            Self::Repeat => write!(f, "fn repeat(str, count) {{ BUILTIN }}"),
+            Self::Chars => write!(f, "fn chars(str) {{ BUILTIN }}"),
+            Self::PadLeft => write!(f, "fn pad_left(str, width, pad?) {{ BUILTIN }}"),
+            Self::PadRight => write!(f, "fn pad_right(str, width, pad?) {{ BUILTIN }}"),
+            Self::Format => write!(f, "fn format(template, ...args) {{ BUILTIN }}"),
+            Self::Keys => write!(f, "fn keys(hash) {{ BUILTIN }}"),
+            Self::Values => write!(f, "fn values(hash) {{ BUILTIN }}"),
+            Self::Entries => write!(f, "fn entries(hash) {{ BUILTIN }}"),
+            Self::Has => write!(f, "fn has(hash, key) {{ BUILTIN }}"),
+            Self::Merge => write!(f, "fn merge(hash, other) {{ BUILTIN }}"),
+            Self::Remove => write!(f, "fn remove(hash, key) {{ BUILTIN }}"),
+            Self::FromEntries => write!(f, "fn from_entries(array) {{ BUILTIN }}"),
+            Self::Type => write!(f, "fn type(obj) {{ BUILTIN }}"),
+            Self::Str => write!(f, "fn str(obj) {{ BUILTIN }}"),
+            Self::Int => write!(f, "fn int(obj) {{ BUILTIN }}"),
+            Self::Bool => write!(f, "fn bool(obj) {{ BUILTIN }}"),
+            Self::IsInt => write!(f, "fn is_int(obj) {{ BUILTIN }}"),
+            Self::IsBool => write!(f, "fn is_bool(obj) {{ BUILTIN }}"),
+            Self::IsStr => write!(f, "fn is_str(obj) {{ BUILTIN }}"),
+            Self::IsNull => write!(f, "fn is_null(obj) {{ BUILTIN }}"),
+            Self::IsArray => write!(f, "fn is_array(obj) {{ BUILTIN }}"),
+            Self::IsHash => write!(f, "fn is_hash(obj) {{ BUILTIN }}"),
+            Self::IsRange => write!(f, "fn is_range(obj) {{ BUILTIN }}"),
+            Self::IsCallable => write!(f, "fn is_callable(obj) {{ BUILTIN }}"),
+            #[cfg(feature = "regex")]
+            Self::ReMatch => write!(f, "fn re_match(str, pattern) {{ BUILTIN }}"),
+            #[cfg(feature = "regex")]
+            Self::ReFindAll => write!(f, "fn re_find_all(str, pattern) {{ BUILTIN }}"),
+            #[cfg(feature = "regex")]
+            Self::ReReplace => write!(f, "fn re_replace(str, pattern, replacement) {{ BUILTIN }}"),
+            #[cfg(feature = "regex")]
+            Self::ReSplit => write!(f, "fn re_split(str, pattern) {{ BUILTIN }}"),
         }
     }
 }
 
 impl Builtin {
-    pub fn call(&self, args: Vec<Object>, env: HeapEnvironment) -> Result<Object> {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Len => "len",
+            Self::Del => "del",
+            Self::Rest => "rest",
+            Self::Push => "push",
+            Self::Puts => "puts",
+            Self::Eputs => "eputs",
+            Self::Input => "input",
+            Self::Error => "error",
+            Self::Time => "time",
+            Self::Random => "random",
+            Self::ReadFile => "read_file",
+            Self::WriteFile => "write_file",
+            #[cfg(feature = "serde")]
+            Self::JsonParse => "json_parse",
+            #[cfg(feature = "serde")]
+            Self::JsonStringify => "json_stringify",
+            Self::Map => "map",
+            Self::Filter => "filter",
+            Self::Reduce => "reduce",
+            Self::Sort => "sort",
+            Self::SortBy => "sort_by",
+            Self::Any => "any",
+            Self::All => "all",
+            Self::Find => "find",
+            Self::Zip => "zip",
+            Self::Enumerate => "enumerate",
+            Self::Split => "split",
+            Self::Join => "join",
+            Self::Trim => "trim",
+            Self::Upper => "upper",
+            Self::Lower => "lower",
+            Self::Replace => "replace",
+            Self::Contains => "contains",
+            Self::StartsWith => "starts_with",
+            Self::EndsWith => "ends_with",
+            Self::IndexOf => "index_of",
+            Self::Repeat => "repeat",
+            Self::Chars => "chars",
+            Self::PadLeft => "pad_left",
+            Self::PadRight => "pad_right",
+            Self::Format => "format",
+            Self::Keys => "keys",
+            Self::Values => "values",
+            Self::Entries => "entries",
+            Self::Has => "has",
+            Self::Merge => "merge",
+            Self::Remove => "remove",
+            Self::FromEntries => "from_entries",
+            Self::Type => "type",
+            Self::Str => "str",
+            Self::Int => "int",
+            Self::Bool => "bool",
+            Self::IsInt => "is_int",
+            Self::IsBool => "is_bool",
+            Self::IsStr => "is_str",
+            Self::IsNull => "is_null",
+            Self::IsArray => "is_array",
+            Self::IsHash => "is_hash",
+            Self::IsRange => "is_range",
+            Self::IsCallable => "is_callable",
+            #[cfg(feature = "regex")]
+            Self::ReMatch => "re_match",
+            #[cfg(feature = "regex")]
+            Self::ReFindAll => "re_find_all",
+            #[cfg(feature = "regex")]
+            Self::ReReplace => "re_replace",
+            #[cfg(feature = "regex")]
+            Self::ReSplit => "re_split",
+        }
+    }
+
+    /// The capability a script needs to call this builtin, if any.
+    pub fn capability(&self) -> Option<Capability> {
+        match self {
+            Self::Puts | Self::Eputs | Self::Input => Some(Capability::Io),
+            Self::Del => Some(Capability::EnvMutation),
+            Self::Time => Some(Capability::Time),
+            Self::Random => Some(Capability::Random),
+            Self::ReadFile | Self::WriteFile => Some(Capability::Fs),
+            _ => None,
+        }
+    }
+
+    /// Runs the builtin, called at `location`.
+    pub fn call(
+        &self,
+        args: Vec<Object>,
+        location: Location,
+        env: HeapEnvironment,
+        ctx: &mut Context,
+    ) -> Result<Object> {
+        if let Some(capability) = self.capability() {
+            if !ctx.allows(capability) {
+                return Err(Error::PermissionError {
+                    builtin: self.name().into(),
+                    capability,
+                });
+            }
+        }
+
         match self {
             Self::Len => {
                 let expected = 1;
@@ -39,11 +313,17 @@ impl Builtin {
 
                 match &args[0] {
                     Object::Str(s) => Ok(Object::Integer(
-                        s.len().try_into().expect("STRING received is too long"),
+                        s.chars()
+                            .count()
+                            .try_into()
+                            .expect("STRING received is too long"),
                     )),
                     Object::Array(a) => Ok(Object::Integer(
                         a.len().try_into().expect("ARRAY received is too long"),
                     )),
+                    Object::Range(r) => Ok(Object::Integer(
+                        r.len()?.try_into().expect("RANGE received is too long"),
+                    )),
                     o => Err(Error::TypeError {
                         expected: ObjectType::Array,
                         received: o.into(),
@@ -58,7 +338,12 @@ impl Builtin {
                 }
 
                 match &args[0] {
-                    Object::Str(s) => Ok(env.borrow_mut().remove(s).unwrap_or(Object::Null)),
+                    // Locals are resolved to slots, only names in a store can be removed
+                    Object::Str(s) => env.borrow_mut().remove(s).ok_or_else(|| {
+                        Error::ValueError(format!(
+                            "Only variables declared at the top level can be deleted, {s} isn't one"
+                        ))
+                    }),
                     o => Err(Error::TypeError {
                         expected: ObjectType::Str,
                         received: o.into(),
@@ -73,14 +358,15 @@ impl Builtin {
                 }
 
                 match &args[0] {
-                    Object::Array(v) => {
-                        let mut new_vec = Vec::new();
+                    Object::Array(v) if v.is_empty() => Ok(Object::Array(v.clone())),
+                    Object::Array(v) => Ok(Object::Array(v.skip(1))),
+                    Object::Str(s) => Ok(Object::Str(s.chars().skip(1).collect())),
+                    Object::Range(r) => {
+                        ctx.check_allocation(r.len()?)?;
 
-                        for o in v.iter().skip(1) {
-                            new_vec.push(o.clone())
-                        }
-
-                        Ok(Object::Array(new_vec))
+                        Ok(Object::Array(
+                            r.iter()?.skip(1).map(Object::Integer).collect(),
+                        ))
                     }
                     o => Err(Error::TypeError {
                         expected: ObjectType::Array,
@@ -97,9 +383,11 @@ impl Builtin {
 
                 match &args[0] {
                     Object::Array(v) => {
+                        ctx.check_allocation(v.len() + 1)?;
+
                         let mut new_vec = v.clone();
 
-                        new_vec.push(args[1].clone());
+                        new_vec.push_back(args[1].clone());
 
                         Ok(Object::Array(new_vec))
                     }
@@ -109,39 +397,1131 @@ impl Builtin {
                     }),
                 }
             }
-            Self::Puts => {
-                if args.is_empty() {
-                    println!();
-                } else {
-                    for arg in args {
-                        println!("{arg}");
+            Self::Puts => write_lines(ctx.stdout(), &args),
+            Self::Eputs => write_lines(ctx.stderr(), &args),
+            Self::Input => {
+                let expected = 1;
+                let received = args.len();
+                if received > expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match args.first() {
+                    Some(Object::Str(prompt)) => {
+                        let stdout = ctx.stdout();
+                        write!(stdout, "{prompt}")
+                            .and_then(|_| stdout.flush())
+                            .map_err(|e| Error::IOError(e.to_string()))?;
+                    }
+                    Some(o) => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Str,
+                            received: o.into(),
+                        })
+                    }
+                    None => (),
+                }
+
+                match ctx.read_line() {
+                    Ok(Some(line)) => Ok(Object::Str(line)),
+                    Ok(None) => Ok(Object::Null),
+                    Err(e) => Err(Error::IOError(e.to_string())),
+                }
+            }
+            Self::Error => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match (&args[0], &args[1]) {
+                    (Object::Str(kind), Object::Str(message)) => Ok(error_object(kind, message)),
+                    (Object::Str(_), o) | (o, _) => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            Self::Time => {
+                let expected = 0;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let elapsed = SystemTime::now().duration_since(UNIX_EPOCH).map_err(|_| {
+                    Error::ValueError("System clock is set before the UNIX epoch".into())
+                })?;
+
+                Ok(Object::Integer(elapsed.as_millis() as i64))
+            }
+            Self::Random => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Integer(max) if *max > 0 => {
+                        Ok(Object::Integer((ctx.next_random() % *max as u64) as i64))
+                    }
+                    Object::Integer(max) => Err(Error::ValueError(format!(
+                        "random expects a positive maximum, received {max}"
+                    ))),
+                    o => Err(Error::TypeError {
+                        expected: ObjectType::Integer,
+                        received: o.into(),
+                    }),
+                }
+            }
+            Self::ReadFile => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Str(path) => fs::read_to_string(path)
+                        .map(Object::Str)
+                        .map_err(|e| Error::IOError(format!("{path}: {e}"))),
+                    o => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            Self::WriteFile => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match (&args[0], &args[1]) {
+                    (Object::Str(path), Object::Str(contents)) => fs::write(path, contents)
+                        .map(|_| Object::Null)
+                        .map_err(|e| Error::IOError(format!("{path}: {e}"))),
+                    (Object::Str(_), o) | (o, _) => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            #[cfg(feature = "serde")]
+            Self::JsonParse => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Str(s) => serde_json::from_str(s)
+                        .map_err(|e| Error::ValueError(format!("Invalid JSON: {e}"))),
+                    o => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            #[cfg(feature = "serde")]
+            Self::JsonStringify => {
+                let expected = 2;
+                let received = args.len();
+                if received == 0 || received > expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let indent = match args.get(1) {
+                    Some(Object::Integer(i)) if *i >= 0 => Some(*i as usize),
+                    Some(Object::Integer(i)) => {
+                        return Err(Error::ValueError(format!(
+                            "json_stringify expects a non-negative indent, received {i}"
+                        )))
+                    }
+                    Some(o) => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Integer,
+                            received: o.into(),
+                        })
+                    }
+                    None => None,
+                };
+
+                json_stringify(&args[0], indent).map(Object::Str)
+            }
+            Self::Map => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Hash(h) => {
+                        let mut mapped = hash::Hash::with_capacity(h.len());
+
+                        for (k, v) in h.iter() {
+                            let value =
+                                self.call_back(&args[1], entry(k, v), location, &env, ctx)?;
+                            mapped.insert(k.clone(), value);
+                        }
+
+                        Ok(Object::Hash(Rc::new(mapped)))
+                    }
+                    sequence => {
+                        let mut mapped = Array::new();
+
+                        for e in elements(sequence, ctx)? {
+                            mapped.push_back(self.call_back(
+                                &args[1],
+                                vec![e],
+                                location,
+                                &env,
+                                ctx,
+                            )?);
+                        }
+
+                        Ok(Object::Array(mapped))
+                    }
+                }
+            }
+            Self::Filter => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Hash(h) => {
+                        let mut kept = hash::Hash::new();
+
+                        for (k, v) in h.iter() {
+                            if self.test(&args[1], entry(k, v), location, &env, ctx)? {
+                                kept.insert(k.clone(), v.clone());
+                            }
+                        }
+
+                        Ok(Object::Hash(Rc::new(kept)))
+                    }
+                    sequence => {
+                        let mut kept = Array::new();
+
+                        for e in elements(sequence, ctx)? {
+                            if self.test(&args[1], vec![e.clone()], location, &env, ctx)? {
+                                kept.push_back(e);
+                            }
+                        }
+
+                        Ok(Object::Array(kept))
+                    }
+                }
+            }
+            Self::Reduce => {
+                let expected = 3;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let mut accumulated = args[1].clone();
+
+                match &args[0] {
+                    Object::Hash(h) => {
+                        for (k, v) in h.iter() {
+                            let arguments = vec![accumulated, k.clone().into(), v.clone()];
+                            accumulated =
+                                self.call_back(&args[2], arguments, location, &env, ctx)?;
+                        }
+                    }
+                    sequence => {
+                        for e in elements(sequence, ctx)? {
+                            let arguments = vec![accumulated, e];
+                            accumulated =
+                                self.call_back(&args[2], arguments, location, &env, ctx)?;
+                        }
+                    }
+                }
+
+                Ok(accumulated)
+            }
+            Self::Sort => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let mut sorted = elements(&args[0], ctx)?;
+                check_sortable(sorted.iter())?;
+                sorted.sort_by(compare);
+
+                Ok(Object::Array(sorted))
+            }
+            Self::SortBy => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Hash(h) => {
+                        let mut keyed = Vec::with_capacity(h.len());
+
+                        for (k, v) in h.iter() {
+                            let key = self.call_back(&args[1], entry(k, v), location, &env, ctx)?;
+                            keyed.push((key, (k.clone(), v.clone())));
+                        }
+
+                        check_sortable(keyed.iter().map(|(key, _)| key))?;
+                        keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+
+                        let sorted = keyed.into_iter().map(|(_, entry)| entry).collect();
+                        Ok(Object::Hash(Rc::new(sorted)))
+                    }
+                    sequence => {
+                        let elements = elements(sequence, ctx)?;
+                        let mut keyed = Vec::with_capacity(elements.len());
+
+                        for e in elements {
+                            let key =
+                                self.call_back(&args[1], vec![e.clone()], location, &env, ctx)?;
+                            keyed.push((key, e));
+                        }
+
+                        check_sortable(keyed.iter().map(|(key, _)| key))?;
+                        keyed.sort_by(|(a, _), (b, _)| compare(a, b));
+
+                        Ok(Object::Array(keyed.into_iter().map(|(_, e)| e).collect()))
+                    }
+                }
+            }
+            Self::Any | Self::All | Self::Find => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                // `all` looks for an element failing the predicate
+                let wanted = *self != Self::All;
+                let holds = |arguments, ctx: &mut Context| {
+                    Ok(self.test(&args[1], arguments, location, &env, ctx)? == wanted)
+                };
+
+                // Candidates are built one at a time, stopping at the first match
+                let found = match &args[0] {
+                    Object::Hash(h) => first(h.iter(), |(k, v)| entry(k, v), holds, ctx)?
+                        .map(|(k, v)| Object::Array(entry(k, v).into())),
+                    Object::Array(a) => first(a.iter(), |&e| vec![e.clone()], holds, ctx)?.cloned(),
+                    Object::Range(r) => {
+                        first(r.iter()?, |&i| vec![Object::Integer(i)], holds, ctx)?
+                            .map(Object::Integer)
+                    }
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Array,
+                            received: o.into(),
+                        })
                     }
+                };
+
+                Ok(match (self, found) {
+                    (Self::Find, found) => found.unwrap_or(Object::Null),
+                    (_, found) => Object::Boolean(found.is_some() == wanted),
+                })
+            }
+            Self::Zip => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
                 }
 
-                Ok(Object::Null)
+                let left = elements(&args[0], ctx)?;
+                let right = elements(&args[1], ctx)?;
+
+                Ok(Object::Array(
+                    left.into_iter()
+                        .zip(right)
+                        .map(|(l, r)| Object::Array(Array::from_iter([l, r])))
+                        .collect(),
+                ))
+            }
+            Self::Enumerate => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                Ok(Object::Array(
+                    elements(&args[0], ctx)?
+                        .into_iter()
+                        .zip(0..)
+                        .map(|(e, i)| Object::Array(Array::from_iter([Object::Integer(i), e])))
+                        .collect(),
+                ))
+            }
+            Self::Split => {
+                let expected = 2;
+                let received = args.len();
+                if received == 0 || received > expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let s = string(&args[0])?;
+
+                let parts: Array = match args.get(1).map(string).transpose()? {
+                    None => s.split_whitespace().map(Object::from).collect(),
+                    Some("") => s.chars().map(|c| Object::Str(c.into())).collect(),
+                    Some(separator) => s.split(separator).map(Object::from).collect(),
+                };
+
+                Ok(Object::Array(parts))
+            }
+            Self::Join => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let separator = string(&args[1])?;
+
+                let parts = match &args[0] {
+                    Object::Array(a) => a.iter().map(string).collect::<Result<Vec<_>>>()?,
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Array,
+                            received: o.into(),
+                        })
+                    }
+                };
+
+                let separators = separator.len().checked_mul(parts.len().saturating_sub(1));
+                let size = separators.and_then(|separators| {
+                    parts
+                        .iter()
+                        .try_fold(separators, |size, part| size.checked_add(part.len()))
+                });
+                ctx.check_allocation(self.string_size(size)?)?;
+
+                Ok(Object::Str(parts.join(separator)))
+            }
+            Self::Trim | Self::Upper | Self::Lower | Self::Chars => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let s = string(&args[0])?;
+
+                Ok(match self {
+                    Self::Trim => Object::from(s.trim()),
+                    Self::Upper => Object::Str(s.to_uppercase()),
+                    Self::Lower => Object::Str(s.to_lowercase()),
+                    _ => Object::Array(s.chars().map(|c| Object::Str(c.into())).collect()),
+                })
+            }
+            Self::Replace => {
+                let expected = 3;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let (s, from, to) = (string(&args[0])?, string(&args[1])?, string(&args[2])?);
+
+                // An empty pattern matches around every character
+                let growth = to.len().saturating_sub(from.len());
+                if growth > 0 {
+                    let matches = match from.is_empty() {
+                        true => s.chars().count() + 1,
+                        false => s.matches(from).count(),
+                    };
+                    let size = matches
+                        .checked_mul(growth)
+                        .and_then(|growth| growth.checked_add(s.len()));
+                    ctx.check_allocation(self.string_size(size)?)?;
+                }
+
+                Ok(Object::Str(s.replace(from, to)))
+            }
+            Self::Contains => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match (&args[0], &args[1]) {
+                    (Object::Str(s), item) => Ok(Object::Boolean(s.contains(string(item)?))),
+                    (Object::Array(a), item) => Ok(Object::Boolean(a.contains(item))),
+                    (Object::Range(r), Object::Integer(i)) => {
+                        Ok(Object::Boolean(r.iter()?.contains(i)))
+                    }
+                    (Object::Range(_), o) => Err(Error::TypeError {
+                        expected: ObjectType::Integer,
+                        received: o.into(),
+                    }),
+                    (o, _) => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            Self::StartsWith | Self::EndsWith => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let (s, affix) = (string(&args[0])?, string(&args[1])?);
+
+                Ok(Object::Boolean(match self {
+                    Self::StartsWith => s.starts_with(affix),
+                    _ => s.ends_with(affix),
+                }))
+            }
+            Self::IndexOf => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let (s, substring) = (string(&args[0])?, string(&args[1])?);
+
+                // Indexes count characters like indexing a string does
+                let index = match s.find(substring) {
+                    Some(byte) => s[..byte].chars().count() as i64,
+                    None => -1,
+                };
+
+                Ok(Object::Integer(index))
+            }
+            Self::Repeat => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let s = string(&args[0])?;
+                let count = match &args[1] {
+                    Object::Integer(i) if *i >= 0 => *i as usize,
+                    Object::Integer(i) => {
+                        return Err(Error::ValueError(format!(
+                            "repeat expects a non-negative count, received {i}"
+                        )))
+                    }
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Integer,
+                            received: o.into(),
+                        })
+                    }
+                };
+
+                ctx.check_allocation(self.string_size(s.len().checked_mul(count))?)?;
+
+                Ok(Object::Str(s.repeat(count)))
+            }
+            Self::PadLeft | Self::PadRight => {
+                let expected = 3;
+                let received = args.len();
+                if received < 2 || received > expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let s = string(&args[0])?;
+                let width = match &args[1] {
+                    Object::Integer(i) => usize::try_from(*i).unwrap_or(0),
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Integer,
+                            received: o.into(),
+                        })
+                    }
+                };
+
+                let pad = match args.get(2).map(string).transpose()? {
+                    None => ' ',
+                    Some(pad) => {
+                        let mut chars = pad.chars();
+
+                        match (chars.next(), chars.next()) {
+                            (Some(c), None) => c,
+                            _ => {
+                                return Err(Error::ValueError(format!(
+                                    "{} expects a single character to pad with, received \"{pad}\"",
+                                    self.name()
+                                )))
+                            }
+                        }
+                    }
+                };
+
+                let padding = width.saturating_sub(s.chars().count());
+                let size = padding
+                    .checked_mul(pad.len_utf8())
+                    .and_then(|padding| padding.checked_add(s.len()));
+                ctx.check_allocation(self.string_size(size)?)?;
+
+                let padding: String = std::iter::repeat_n(pad, padding).collect();
+
+                Ok(Object::Str(match self {
+                    Self::PadLeft => padding + s,
+                    _ => s.to_string() + &padding,
+                }))
+            }
+            Self::Format => {
+                let Some(template) = args.first() else {
+                    return Err(Error::TooFewArgumentsError {
+                        minimum: 1,
+                        received: 0,
+                    });
+                };
+                let template = string(template)?;
+
+                // Escapes only shrink the template, so this bounds the result
+                let args: Vec<_> = args[1..].iter().map(ToString::to_string).collect();
+                let size = args
+                    .iter()
+                    .try_fold(template.len(), |size, arg| size.checked_add(arg.len()));
+                ctx.check_allocation(self.string_size(size)?)?;
+
+                Ok(Object::Str(format(template, &args)?))
+            }
+            Self::Keys | Self::Values | Self::Entries => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let h = hash_table(&args[0])?;
+
+                Ok(Object::Array(match self {
+                    Self::Keys => h.keys().cloned().map(Object::from).collect(),
+                    Self::Values => h.values().cloned().collect(),
+                    _ => h
+                        .iter()
+                        .map(|(k, v)| Object::Array(entry(k, v).into()))
+                        .collect(),
+                }))
+            }
+            Self::Has => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let h = hash_table(&args[0])?;
+
+                Ok(Object::Boolean(
+                    h.contains_key(&HashableObject::try_from(&args[1])?),
+                ))
+            }
+            Self::Merge => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let (h, other) = (hash_table(&args[0])?, hash_table(&args[1])?);
+                ctx.check_allocation(h.len() + other.len())?;
+
+                // Keys of `other` win, keeping their place in `h`
+                let mut merged = h.as_ref().clone();
+                merged.extend(other.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+                Ok(Object::Hash(Rc::new(merged)))
+            }
+            Self::Remove => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let h = hash_table(&args[0])?;
+                let key = HashableObject::try_from(&args[1])?;
+
+                let mut removed = h.as_ref().clone();
+                removed.shift_remove(&key);
+
+                Ok(Object::Hash(Rc::new(removed)))
+            }
+            Self::FromEntries => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let entries = match &args[0] {
+                    Object::Array(a) => a,
+                    o => {
+                        return Err(Error::TypeError {
+                            expected: ObjectType::Array,
+                            received: o.into(),
+                        })
+                    }
+                };
+
+                let mut h = hash::Hash::with_capacity(entries.len());
+
+                for e in entries {
+                    let (key, value) = <(Object, Object)>::try_from(e.clone())?;
+                    h.insert(HashableObject::try_from(&key)?, value);
+                }
+
+                Ok(Object::Hash(Rc::new(h)))
+            }
+            Self::Type | Self::Str | Self::Bool => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                Ok(match self {
+                    Self::Type => Object::Str(ObjectType::from(&args[0]).to_string()),
+                    Self::Str => Object::Str(args[0].to_string()),
+                    _ => Object::Boolean(is_truthy(args[0].clone())),
+                })
+            }
+            Self::Int => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                match &args[0] {
+                    Object::Integer(i) => Ok(Object::Integer(*i)),
+                    Object::Boolean(b) => Ok(Object::Integer(*b as i64)),
+                    Object::Str(s) => s.parse().map(Object::Integer).map_err(|_| {
+                        Error::ValueError(format!(
+                            "int expects a decimal integer, received \"{s}\""
+                        ))
+                    }),
+                    o => Err(Error::TypeError {
+                        expected: ObjectType::Str,
+                        received: o.into(),
+                    }),
+                }
+            }
+            Self::IsInt
+            | Self::IsBool
+            | Self::IsStr
+            | Self::IsNull
+            | Self::IsArray
+            | Self::IsHash
+            | Self::IsRange
+            | Self::IsCallable => {
+                let expected = 1;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                Ok(Object::Boolean(matches!(
+                    (self, &args[0]),
+                    (Self::IsInt, Object::Integer(_))
+                        | (Self::IsBool, Object::Boolean(_))
+                        | (Self::IsStr, Object::Str(_))
+                        | (Self::IsNull, Object::Null)
+                        | (Self::IsArray, Object::Array(_))
+                        | (Self::IsHash, Object::Hash(_))
+                        | (Self::IsRange, Object::Range(_))
+                        | (
+                            Self::IsCallable,
+                            Object::Function(_) | Object::Builtin(_) | Object::Native(_)
+                        )
+                )))
+            }
+            #[cfg(feature = "regex")]
+            Self::ReMatch | Self::ReFindAll | Self::ReSplit => {
+                let expected = 2;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let s = string(&args[0])?;
+                let regex = ctx.patterns().compile(string(&args[1])?)?;
+                let mut indexes = CharIndexes::new(s);
+
+                Ok(match self {
+                    Self::ReMatch => regex
+                        .captures(s)
+                        .map_or(Object::Null, |c| match_object(&regex, &c, &mut indexes)),
+                    Self::ReFindAll => Object::Array(
+                        regex
+                            .captures_iter(s)
+                            .map(|c| match_object(&regex, &c, &mut indexes))
+                            .collect(),
+                    ),
+                    _ => Object::Array(regex.split(s).map(Object::from).collect()),
+                })
+            }
+            #[cfg(feature = "regex")]
+            Self::ReReplace => {
+                let expected = 3;
+                let received = args.len();
+                if received != expected {
+                    return Err(Error::ArgumentsError { expected, received });
+                }
+
+                let (s, replacement) = (string(&args[0])?, string(&args[2])?);
+                let regex = ctx.patterns().compile(string(&args[1])?)?;
+
+                // Built a match at a time so the limit is checked as it grows
+                let mut replaced = String::new();
+                let mut last = 0;
+
+                for captures in regex.captures_iter(s) {
+                    let matched = captures.get(0).expect("group 0 is the whole match");
+
+                    replaced.push_str(&s[last..matched.start()]);
+                    // `$1` and `${name}` in the replacement expand to groups
+                    captures.expand(replacement, &mut replaced);
+                    ctx.check_allocation(replaced.len())?;
+
+                    last = matched.end();
+                }
+                replaced.push_str(&s[last..]);
+                ctx.check_allocation(replaced.len())?;
+
+                Ok(Object::Str(replaced))
             }
         }
     }
 
+    /// Calls `function` back with `arguments`, from a call to this builtin
+    /// at `location`.
+    fn call_back(
+        &self,
+        function: &Object,
+        arguments: Vec<Object>,
+        location: Location,
+        env: &HeapEnvironment,
+        ctx: &mut Context,
+    ) -> Result<Object> {
+        call(
+            function.clone(),
+            arguments,
+            location,
+            CallSite::Callback(self.name()),
+            env.clone(),
+            ctx,
+        )
+    }
+
+    /// Whether the predicate `function` holds for `arguments`.
+    fn test(
+        &self,
+        function: &Object,
+        arguments: Vec<Object>,
+        location: Location,
+        env: &HeapEnvironment,
+        ctx: &mut Context,
+    ) -> Result<bool> {
+        self.call_back(function, arguments, location, env, ctx)
+            .map(is_truthy)
+    }
+
+    /// The `size` in bytes of a string this builtin builds, if it was
+    /// computed without overflowing and such a string can exist.
+    fn string_size(&self, size: Option<usize>) -> Result<usize> {
+        size.filter(|&size| isize::try_from(size).is_ok())
+            .ok_or_else(|| Error::ValueError(format!("Result of {} is too large", self.name())))
+    }
+
     pub fn environment() -> HeapEnvironment {
         let env = Environment::default();
         let heap_env = Rc::new(RefCell::new(env));
 
         let builtins = vec![
-            ("len", Self::Len),
-            ("del", Self::Del),
-            ("rest", Self::Rest),
-            ("push", Self::Push),
-            ("puts", Self::Puts),
+            Self::Len,
+            Self::Del,
+            Self::Rest,
+            Self::Push,
+            Self::Puts,
+            Self::Eputs,
+            Self::Input,
+            Self::Error,
+            Self::Time,
+            Self::Random,
+            Self::ReadFile,
+            Self::WriteFile,
+            #[cfg(feature = "serde")]
+            Self::JsonParse,
+            #[cfg(feature = "serde")]
+            Self::JsonStringify,
+            Self::Map,
+            Self::Filter,
+            Self::Reduce,
+            Self::Sort,
+            Self::SortBy,
+            Self::Any,
+            Self::All,
+            Self::Find,
+            Self::Zip,
+            Self::Enumerate,
+            Self::Split,
+            Self::Join,
+            Self::Trim,
+            Self::Upper,
+            Self::Lower,
+            Self::Replace,
+            Self::Contains,
+            Self::StartsWith,
+            Self::EndsWith,
+            Self::IndexOf,
+            Self::Repeat,
+            Self::Chars,
+            Self::PadLeft,
+            Self::PadRight,
+            Self::Format,
+            Self::Keys,
+            Self::Values,
+            Self::Entries,
+            Self::Has,
+            Self::Merge,
+            Self::Remove,
+            Self::FromEntries,
+            Self::Type,
+            Self::Str,
+            Self::Int,
+            Self::Bool,
+            Self::IsInt,
+            Self::IsBool,
+            Self::IsStr,
+            Self::IsNull,
+            Self::IsArray,
+            Self::IsHash,
+            Self::IsRange,
+            Self::IsCallable,
+            #[cfg(feature = "regex")]
+            Self::ReMatch,
+            #[cfg(feature = "regex")]
+            Self::ReFindAll,
+            #[cfg(feature = "regex")]
+            Self::ReReplace,
+            #[cfg(feature = "regex")]
+            Self::ReSplit,
         ];
 
-        for (ident, val) in builtins {
+        for val in builtins {
             heap_env
                 .borrow_mut()
                 .store
-                .insert(ident.into(), Object::Builtin(val));
+                .insert(val.name().into(), Object::Builtin(val));
         }
 
         heap_env
     }
 }
+
+/// Writes each object on its own line, or an empty line without arguments.
+fn write_lines(out: &mut dyn Write, args: &[Object]) -> Result<Object> {
+    let res = if args.is_empty() {
+        writeln!(out)
+    } else {
+        args.iter().try_for_each(|arg| writeln!(out, "{arg}"))
+    };
+
+    res.map_err(|e| Error::IOError(e.to_string()))?;
+
+    Ok(Object::Null)
+}
+
+#[cfg(feature = "serde")]
+fn json_stringify(object: &Object, indent: Option<usize>) -> Result<String> {
+    use serde::Serialize;
+
+    let mut buf = Vec::new();
+
+    let res = match indent {
+        Some(n) => {
+            let indent = vec![b' '; n];
+            let formatter = serde_json::ser::PrettyFormatter::with_indent(&indent);
+
+            object.serialize(&mut serde_json::Serializer::with_formatter(
+                &mut buf, formatter,
+            ))
+        }
+        None => object.serialize(&mut serde_json::Serializer::new(&mut buf)),
+    };
+
+    res.map_err(|e| Error::ValueError(e.to_string()))?;
+
+    Ok(String::from_utf8(buf).expect("serde_json only writes UTF-8"))
+}
+
+/// Elements of a sequence builtins iterate over, ranges are expanded.
+fn elements(sequence: &Object, ctx: &mut Context) -> Result<Array> {
+    match sequence {
+        Object::Array(a) => Ok(a.clone()),
+        Object::Range(r) => {
+            ctx.check_allocation(r.len()?)?;
+
+            Ok(r.iter()?.map(Object::Integer).collect())
+        }
+        o => Err(Error::TypeError {
+            expected: ObjectType::Array,
+            received: o.into(),
+        }),
+    }
+}
+
+/// The first of `candidates` that `holds` for the arguments built for it.
+fn first<T>(
+    candidates: impl Iterator<Item = T>,
+    arguments: impl Fn(&T) -> Vec<Object>,
+    mut holds: impl FnMut(Vec<Object>, &mut Context) -> Result<bool>,
+    ctx: &mut Context,
+) -> Result<Option<T>> {
+    for candidate in candidates {
+        if holds(arguments(&candidate), ctx)? {
+            return Ok(Some(candidate));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Arguments callbacks receive for an entry of a hash.
+fn entry(key: &HashableObject, value: &Object) -> Vec<Object> {
+    vec![key.clone().into(), value.clone()]
+}
+
+/// Only integers, strings, booleans and arrays can be sorted, all of the same
+/// type.
+fn check_sortable<'a>(mut objects: impl Iterator<Item = &'a Object>) -> Result<()> {
+    let Some(first) = objects.next() else {
+        return Ok(());
+    };
+
+    let expected = ObjectType::from(first);
+    if !matches!(
+        expected,
+        ObjectType::Integer | ObjectType::Str | ObjectType::Boolean | ObjectType::Array
+    ) {
+        return Err(Error::TypeError {
+            expected: ObjectType::Integer,
+            received: expected,
+        });
+    }
+
+    match objects.find(|o| ObjectType::from(*o) != expected) {
+        Some(o) => Err(Error::TypeError {
+            expected,
+            received: o.into(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Orders objects by value, arrays lexicographically. Objects of different
+/// types are ordered by type, so sorting never fails on nested arrays.
+fn compare(a: &Object, b: &Object) -> Ordering {
+    match (a, b) {
+        (Object::Integer(x), Object::Integer(y)) => x.cmp(y),
+        (Object::Str(x), Object::Str(y)) => x.cmp(y),
+        (Object::Boolean(x), Object::Boolean(y)) => x.cmp(y),
+        (Object::Array(x), Object::Array(y)) => x
+            .iter()
+            .zip(y)
+            .map(|(x, y)| compare(x, y))
+            .find(|o| o.is_ne())
+            .unwrap_or_else(|| x.len().cmp(&y.len())),
+        (a, b) => rank(a).cmp(&rank(b)),
+    }
+}
+
+fn rank(object: &Object) -> u8 {
+    match object {
+        Object::Null => 0,
+        Object::Boolean(_) => 1,
+        Object::Integer(_) => 2,
+        Object::Str(_) => 3,
+        Object::Array(_) => 4,
+        _ => 5,
+    }
+}
+
+/// The string argument `object`, or a type error.
+fn string(object: &Object) -> Result<&str> {
+    match object {
+        Object::Str(s) => Ok(s),
+        o => Err(Error::TypeError {
+            expected: ObjectType::Str,
+            received: o.into(),
+        }),
+    }
+}
+
+/// The hash argument `object`, or a type error.
+fn hash_table(object: &Object) -> Result<&Rc<hash::Hash>> {
+    match object {
+        Object::Hash(h) => Ok(h),
+        o => Err(Error::TypeError {
+            expected: ObjectType::Hash,
+            received: o.into(),
+        }),
+    }
+}
+
+/// Replaces each `{}` in `template` by the next argument, `{{` and `}}`
+/// escape braces.
+fn format(template: &str, args: &[String]) -> Result<String> {
+    let mut formatted = String::with_capacity(template.len());
+    let mut chars = template.chars().peekable();
+    let mut placeholders = 0;
+
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            ('{', Some('}')) => {
+                chars.next();
+
+                if let Some(arg) = args.get(placeholders) {
+                    formatted.push_str(arg);
+                }
+                placeholders += 1;
+            }
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                formatted.push(c);
+            }
+            ('{' | '}', _) => {
+                return Err(Error::ValueError(format!(
+                    "Unmatched {c} in format template \"{template}\""
+                )))
+            }
+            _ => formatted.push(c),
+        }
+    }
+
+    if placeholders != args.len() {
+        return Err(Error::ValueError(format!(
+            "Format template has {placeholders} placeholders, received {} arguments",
+            args.len()
+        )));
+    }
+
+    Ok(formatted)
+}
@@ -0,0 +1,317 @@
+//! Frees environments kept alive only by reference cycles.
+//!
+//! A closure holds the environment it was created in and is usually stored
+//! back into it, so reference counting alone never frees either. Every
+//! environment made by `Environment::new_heap` is tracked, a collection counts
+//! the references between tracked objects to find the ones also referenced
+//! from elsewhere. Those and everything they reach are live, the rest are
+//! cleared, which breaks their cycles.
+//!
+//! Array copies share their storage without counting the references to it,
+//! so an array held outside the graph, by a builtin's arguments for example,
+//! can't be told apart from one held by a tracked environment. Everything
+//! found in an array is assumed to be referenced from elsewhere, cycles going
+//! through arrays are never freed.
+
+use super::environment::{Environment, HeapEnvironment};
+use super::hash::Hash;
+use super::Object;
+
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::rc::{Rc, Weak};
+
+/// Tracked environments before the first automatic collection.
+const MIN_THRESHOLD: usize = 1024;
+
+thread_local! {
+    static TRACKED: RefCell<Tracked> = const {
+        RefCell::new(Tracked {
+            environments: Vec::new(),
+            threshold: MIN_THRESHOLD,
+        })
+    };
+}
+
+struct Tracked {
+    environments: Vec<Weak<RefCell<Environment>>>,
+    /// Collects once this many environments are tracked
+    threshold: usize,
+}
+
+/// Tracks `env`, collecting if enough environments were created since the
+/// last collection.
+pub(super) fn track(env: &HeapEnvironment) {
+    let full = TRACKED.with_borrow_mut(|t| {
+        t.environments.push(Rc::downgrade(env));
+        t.environments.len() >= t.threshold
+    });
+
+    if full {
+        collect();
+    }
+}
+
+/// Number of tracked environments that are still alive.
+pub fn tracked() -> usize {
+    TRACKED.with_borrow(|t| {
+        t.environments
+            .iter()
+            .filter(|e| e.strong_count() > 0)
+            .count()
+    })
+}
+
+/// Frees the environments of this thread only reachable through cycles,
+/// returning how many were freed.
+pub fn collect() -> usize {
+    let environments = TRACKED.with_borrow_mut(|t| {
+        let live: Vec<_> = t.environments.iter().filter_map(Weak::upgrade).collect();
+        t.environments = live.iter().map(Rc::downgrade).collect();
+
+        live
+    });
+
+    let garbage = Graph::new(environments).garbage().unwrap_or_default();
+
+    // The contents are dropped once no environment is borrowed, as dropping
+    // them may free other environments
+    let contents: Vec<_> = garbage
+        .iter()
+        .filter_map(|e| e.try_borrow_mut().ok().map(|mut e| e.clear()))
+        .collect();
+    let freed = contents.len();
+
+    drop(contents);
+    drop(garbage);
+
+    TRACKED.with_borrow_mut(|t| {
+        t.environments.retain(|e| e.strong_count() > 0);
+        t.threshold = MIN_THRESHOLD.max(t.environments.len() * 2);
+    });
+
+    freed
+}
+
+/// An allocation whose references can be counted.
+enum Node {
+    Environment(HeapEnvironment),
+    Hash(Rc<Hash>),
+}
+
+impl Node {
+    fn address(&self) -> *const () {
+        match self {
+            Self::Environment(e) => Rc::as_ptr(e).cast(),
+            Self::Hash(h) => Rc::as_ptr(h).cast(),
+        }
+    }
+
+    /// References held by the graph itself are not counted.
+    fn strong_count(&self) -> usize {
+        match self {
+            Self::Environment(e) => Rc::strong_count(e) - 1,
+            Self::Hash(h) => Rc::strong_count(h) - 1,
+        }
+    }
+}
+
+struct Graph {
+    nodes: Vec<Node>,
+    index: HashMap<*const (), usize>,
+    /// References to every node held by other nodes
+    internal: Vec<usize>,
+    /// Whether a node was found in an array, which may be held from anywhere
+    in_array: Vec<bool>,
+}
+
+impl Graph {
+    fn new(environments: Vec<HeapEnvironment>) -> Self {
+        let mut graph = Self {
+            nodes: Vec::new(),
+            index: HashMap::new(),
+            internal: Vec::new(),
+            in_array: Vec::new(),
+        };
+
+        for env in environments {
+            graph.insert(Node::Environment(env));
+        }
+
+        graph
+    }
+
+    fn insert(&mut self, node: Node) -> usize {
+        let i = self.nodes.len();
+
+        self.index.insert(node.address(), i);
+        self.nodes.push(node);
+        self.internal.push(0);
+        self.in_array.push(false);
+
+        i
+    }
+
+    /// Environments that nothing outside the graph can reach, unknown if an
+    /// environment is being modified.
+    fn garbage(mut self) -> Option<Vec<HeapEnvironment>> {
+        self.count()?;
+        let marked = self.mark()?;
+
+        let garbage = self
+            .nodes
+            .into_iter()
+            .zip(marked)
+            .filter_map(|(node, marked)| match node {
+                Node::Environment(e) if !marked => Some(e),
+                _ => None,
+            })
+            .collect();
+
+        Some(garbage)
+    }
+
+    /// Counts the references between nodes, adding the hashes found as nodes.
+    fn count(&mut self) -> Option<()> {
+        let mut i = 0;
+
+        while i < self.nodes.len() {
+            let mut found = Vec::new();
+
+            match &self.nodes[i] {
+                Node::Environment(env) => {
+                    let env = env.try_borrow().ok()?;
+
+                    found.extend(env.outer().map(|e| (Node::Environment(e.clone()), false)));
+
+                    for value in env.values() {
+                        counted_references(value, false, &mut found);
+                    }
+                }
+                Node::Hash(hash) => {
+                    for value in hash.values() {
+                        counted_references(value, false, &mut found);
+                    }
+                }
+            }
+
+            for (node, in_array) in found {
+                let j = match self.index.get(&node.address()) {
+                    Some(&j) => j,
+                    // Untracked environments are never collected
+                    None if matches!(node, Node::Environment(_)) => continue,
+                    None => self.insert(node),
+                };
+
+                self.internal[j] += 1;
+                self.in_array[j] |= in_array;
+            }
+
+            i += 1;
+        }
+
+        Some(())
+    }
+
+    /// Marks the nodes referenced from outside the graph and everything they
+    /// reach.
+    fn mark(&self) -> Option<Vec<bool>> {
+        let mut marked = vec![false; self.nodes.len()];
+        let mut visited = HashSet::new();
+
+        let mut pending: Vec<_> = (0..self.nodes.len())
+            .filter(|&i| self.in_array[i] || self.nodes[i].strong_count() > self.internal[i])
+            .collect();
+
+        while let Some(i) = pending.pop() {
+            if std::mem::replace(&mut marked[i], true) {
+                continue;
+            }
+
+            self.reachable_from(&self.nodes[i], &mut pending, &mut visited)?;
+        }
+
+        Some(marked)
+    }
+
+    /// Finds the nodes reachable from `node`, going through everything that
+    /// isn't one.
+    fn reachable_from(
+        &self,
+        node: &Node,
+        found: &mut Vec<usize>,
+        visited: &mut HashSet<*const ()>,
+    ) -> Option<()> {
+        match node {
+            Node::Environment(env) => {
+                let env = env.try_borrow().ok()?;
+
+                if let Some(outer) = env.outer() {
+                    self.reachable_node(Node::Environment(outer.clone()), found, visited)?;
+                }
+                for value in env.values() {
+                    self.reachable(value, found, visited)?;
+                }
+            }
+            Node::Hash(hash) => {
+                for value in hash.values() {
+                    self.reachable(value, found, visited)?;
+                }
+            }
+        }
+
+        Some(())
+    }
+
+    fn reachable(
+        &self,
+        object: &Object,
+        found: &mut Vec<usize>,
+        visited: &mut HashSet<*const ()>,
+    ) -> Option<()> {
+        match object {
+            Object::Function(f) => {
+                self.reachable_node(Node::Environment(f.env.clone()), found, visited)
+            }
+            Object::Macro(m) => {
+                self.reachable_node(Node::Environment(m.env.clone()), found, visited)
+            }
+            Object::Hash(h) => self.reachable_node(Node::Hash(h.clone()), found, visited),
+            Object::Array(a) => a
+                .iter()
+                .try_for_each(|element| self.reachable(element, found, visited)),
+            _ => Some(()),
+        }
+    }
+
+    fn reachable_node(
+        &self,
+        node: Node,
+        found: &mut Vec<usize>,
+        visited: &mut HashSet<*const ()>,
+    ) -> Option<()> {
+        match self.index.get(&node.address()) {
+            Some(&i) => found.push(i),
+            None if visited.insert(node.address()) => self.reachable_from(&node, found, visited)?,
+            None => {}
+        }
+
+        Some(())
+    }
+}
+
+/// Collects the nodes referenced by `object` and whether they were found in
+/// an array.
+fn counted_references(object: &Object, in_array: bool, found: &mut Vec<(Node, bool)>) {
+    match object {
+        Object::Function(f) => found.push((Node::Environment(f.env.clone()), in_array)),
+        Object::Macro(m) => found.push((Node::Environment(m.env.clone()), in_array)),
+        Object::Hash(h) => found.push((Node::Hash(h.clone()), in_array)),
+        Object::Array(a) => {
+            for element in a {
+                counted_references(element, true, found);
+            }
+        }
+        _ => {}
+    }
+}
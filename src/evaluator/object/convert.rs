@@ -0,0 +1,343 @@
+//! Conversions between `Object` and plain Rust values, for embedders.
+
+use super::super::error::Error;
+use super::hash::HashableObject;
+use super::{Array, Object, ObjectType};
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::hash::Hash as StdHash;
+use std::rc::Rc;
+
+impl From<Infallible> for Error {
+    fn from(value: Infallible) -> Self {
+        match value {}
+    }
+}
+
+impl From<i64> for Object {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<bool> for Object {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<String> for Object {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<&str> for Object {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<()> for Object {
+    fn from(_: ()) -> Self {
+        Self::Null
+    }
+}
+
+impl<T: Into<Object>> From<Option<T>> for Object {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Self::Null, Into::into)
+    }
+}
+
+impl<T: Into<Object>> From<Vec<T>> for Object {
+    fn from(value: Vec<T>) -> Self {
+        Self::Array(value.into_iter().map(Into::into).collect())
+    }
+}
+
+impl<K: Into<HashableObject>, V: Into<Object>> From<HashMap<K, V>> for Object {
+    fn from(value: HashMap<K, V>) -> Self {
+        Self::Hash(Rc::new(
+            value
+                .into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        ))
+    }
+}
+
+impl TryFrom<Object> for i64 {
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Integer(i) => Ok(i),
+            o => Err(type_error(ObjectType::Integer, &o)),
+        }
+    }
+}
+
+impl TryFrom<Object> for bool {
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Boolean(b) => Ok(b),
+            o => Err(type_error(ObjectType::Boolean, &o)),
+        }
+    }
+}
+
+impl TryFrom<Object> for String {
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Str(s) => Ok(s),
+            o => Err(type_error(ObjectType::Str, &o)),
+        }
+    }
+}
+
+impl TryFrom<Object> for () {
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Null => Ok(()),
+            o => Err(type_error(ObjectType::Null, &o)),
+        }
+    }
+}
+
+/// `null` converts to `None`, anything else must convert to the inner type.
+/// A blanket impl would overlap with `Option<Object>: From<Object>`.
+macro_rules! option_conversion {
+    ($($inner:ty),+) => {
+        $(
+            impl TryFrom<Object> for Option<$inner> {
+                type Error = Error;
+                fn try_from(value: Object) -> Result<Self, Self::Error> {
+                    match value {
+                        Object::Null => Ok(None),
+                        o => Ok(Some(o.try_into()?)),
+                    }
+                }
+            }
+        )+
+    };
+}
+
+option_conversion!(i64, bool, String);
+
+impl<T: TryFrom<Object>> TryFrom<Object> for Option<Vec<T>>
+where
+    Error: From<T::Error>,
+{
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Null => Ok(None),
+            o => Ok(Some(Vec::<T>::try_from(o)?)),
+        }
+    }
+}
+
+impl<T: TryFrom<Object>> TryFrom<Object> for Vec<T>
+where
+    Error: From<T::Error>,
+{
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Array(a) => Ok(a.into_iter().map(T::try_from).collect::<Result<_, _>>()?),
+            o => Err(type_error(ObjectType::Array, &o)),
+        }
+    }
+}
+
+impl<K, V> TryFrom<Object> for HashMap<K, V>
+where
+    K: TryFrom<HashableObject> + Eq + StdHash,
+    V: TryFrom<Object>,
+    Error: From<K::Error> + From<V::Error>,
+{
+    type Error = Error;
+    fn try_from(value: Object) -> Result<Self, Self::Error> {
+        match value {
+            Object::Hash(h) => Rc::unwrap_or_clone(h)
+                .into_iter()
+                .map(|(k, v)| Ok((K::try_from(k)?, V::try_from(v)?)))
+                .collect(),
+            o => Err(type_error(ObjectType::Hash, &o)),
+        }
+    }
+}
+
+/// Tuples convert to arrays of the same length.
+macro_rules! tuple_conversions {
+    ($len:literal; $($name:ident),+) => {
+        impl<$($name: Into<Object>),+> From<($($name,)+)> for Object {
+            #[allow(non_snake_case)]
+            fn from(($($name,)+): ($($name,)+)) -> Self {
+                Self::Array(Array::from_iter([$($name.into()),+]))
+            }
+        }
+
+        impl<$($name: TryFrom<Object>),+> TryFrom<Object> for ($($name,)+)
+        where
+            $(Error: From<$name::Error>),+
+        {
+            type Error = Error;
+            fn try_from(value: Object) -> Result<Self, Self::Error> {
+                match value {
+                    Object::Array(a) if a.len() == $len => {
+                        let mut elems = a.into_iter();
+
+                        Ok(($($name::try_from(elems.next().expect("length was checked"))?,)+))
+                    }
+                    Object::Array(a) => Err(Error::ValueError(format!(
+                        "Expected an array of length {}, received one of length {}",
+                        $len,
+                        a.len()
+                    ))),
+                    o => Err(type_error(ObjectType::Array, &o)),
+                }
+            }
+        }
+    };
+}
+
+tuple_conversions!(1; A);
+tuple_conversions!(2; A, B);
+tuple_conversions!(3; A, B, C);
+tuple_conversions!(4; A, B, C, D);
+
+impl From<i64> for HashableObject {
+    fn from(value: i64) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<bool> for HashableObject {
+    fn from(value: bool) -> Self {
+        Self::Boolean(value)
+    }
+}
+
+impl From<String> for HashableObject {
+    fn from(value: String) -> Self {
+        Self::Str(value)
+    }
+}
+
+impl From<&str> for HashableObject {
+    fn from(value: &str) -> Self {
+        Self::Str(value.into())
+    }
+}
+
+impl From<HashableObject> for Object {
+    fn from(value: HashableObject) -> Self {
+        match value {
+            HashableObject::Integer(i) => Self::Integer(i),
+            HashableObject::Boolean(b) => Self::Boolean(b),
+            HashableObject::Str(s) => Self::Str(s),
+            HashableObject::Null => Self::Null,
+            HashableObject::Array(a) => Self::Array(a.into_iter().map(Into::into).collect()),
+        }
+    }
+}
+
+impl TryFrom<HashableObject> for i64 {
+    type Error = Error;
+    fn try_from(value: HashableObject) -> Result<Self, Self::Error> {
+        Object::from(value).try_into()
+    }
+}
+
+impl TryFrom<HashableObject> for bool {
+    type Error = Error;
+    fn try_from(value: HashableObject) -> Result<Self, Self::Error> {
+        Object::from(value).try_into()
+    }
+}
+
+impl TryFrom<HashableObject> for String {
+    type Error = Error;
+    fn try_from(value: HashableObject) -> Result<Self, Self::Error> {
+        Object::from(value).try_into()
+    }
+}
+
+fn type_error(expected: ObjectType, received: &Object) -> Error {
+    Error::TypeError {
+        expected,
+        received: received.into(),
+    }
+}
+
+/// Implements conversions between a struct and an `Object::Hash` keyed by
+/// its field names. Every field must itself convert to and from `Object`,
+/// missing keys are read as `null`.
+///
+/// ```
+/// use interpret_deez::{hash_struct, Object};
+///
+/// #[derive(Debug, PartialEq)]
+/// struct Point {
+///     x: i64,
+///     y: i64,
+///     label: Option<String>,
+/// }
+///
+/// hash_struct!(Point { x, y, label });
+///
+/// let object = Object::from(Point { x: 1, y: 2, label: None });
+///
+/// assert_eq!(Point::try_from(object), Ok(Point { x: 1, y: 2, label: None }));
+/// ```
+#[macro_export]
+macro_rules! hash_struct {
+    ($name:ident { $($field:ident),* $(,)? }) => {
+        impl From<$name> for $crate::evaluator::object::Object {
+            fn from(value: $name) -> Self {
+                let mut hash = $crate::evaluator::object::hash::Hash::new();
+
+                $(
+                    hash.insert(
+                        stringify!($field).into(),
+                        $crate::evaluator::object::Object::from(value.$field),
+                    );
+                )*
+
+                Self::Hash(hash.into())
+            }
+        }
+
+        impl TryFrom<$crate::evaluator::object::Object> for $name {
+            type Error = $crate::evaluator::error::Error;
+
+            fn try_from(value: $crate::evaluator::object::Object) -> Result<Self, Self::Error> {
+                match value {
+                    $crate::evaluator::object::Object::Hash(hash) => {
+                        let mut hash = ::std::rc::Rc::unwrap_or_clone(hash);
+
+                        Ok(Self {
+                            $(
+                                $field: hash
+                                    .shift_remove(&$crate::evaluator::object::hash::HashableObject::from(
+                                        stringify!($field),
+                                    ))
+                                    .unwrap_or($crate::evaluator::object::Object::Null)
+                                    .try_into()?,
+                            )*
+                        })
+                    }
+                    o => Err($crate::evaluator::error::Error::TypeError {
+                        expected: $crate::evaluator::object::ObjectType::Hash,
+                        received: (&o).into(),
+                    }),
+                }
+            }
+        }
+    };
+}
@@ -1,4 +1,5 @@
 use super::builtins::Builtin;
+use super::collector;
 use super::Object;
 
 use std::cell::RefCell;
@@ -8,6 +9,8 @@ use std::rc::Rc;
 #[derive(Default, Debug, PartialEq, Clone)]
 pub struct Environment {
     pub store: HashMap<String, Object>,
+    /// Values of resolved identifiers, empty until assigned
+    slots: Vec<Option<Object>>,
     outer: Option<HeapEnvironment>,
 }
 
@@ -22,12 +25,31 @@ impl Environment {
 
         Self {
             store: HashMap::new(),
+            slots: Vec::new(),
             outer,
         }
     }
 
+    /// Creates an environment tracked by the cycle collector.
     pub fn new_heap(outer: Option<HeapEnvironment>) -> HeapEnvironment {
-        Rc::new(RefCell::new(Self::new(outer)))
+        let env = Rc::new(RefCell::new(Self::new(outer)));
+        collector::track(&env);
+
+        env
+    }
+
+    pub(super) fn outer(&self) -> Option<&HeapEnvironment> {
+        self.outer.as_ref()
+    }
+
+    /// Every value stored in this environment, not in the outer ones.
+    pub(super) fn values(&self) -> impl Iterator<Item = &Object> {
+        self.store.values().chain(self.slots.iter().flatten())
+    }
+
+    /// Empties the environment, returning what it held.
+    pub(super) fn clear(&mut self) -> Self {
+        std::mem::take(self)
     }
 
     pub fn get(&self, k: &str) -> Option<Object> {
@@ -40,6 +62,36 @@ impl Environment {
         }
     }
 
+    pub fn contains(&self, k: &str) -> bool {
+        self.store.contains_key(k) || self.outer.as_ref().is_some_and(|o| o.borrow().contains(k))
+    }
+
+    /// Reads `slot` of the environment `depth` levels up.
+    pub fn get_slot(&self, depth: usize, slot: usize) -> Option<Object> {
+        match (depth, &self.outer) {
+            (0, _) => self.slots.get(slot).cloned().flatten(),
+            (_, Some(outer)) => outer.borrow().get_slot(depth - 1, slot),
+            (_, None) => None,
+        }
+    }
+
+    pub fn set_slot(&mut self, slot: usize, v: Object) {
+        if slot >= self.slots.len() {
+            self.slots.resize(slot + 1, None);
+        }
+
+        self.slots[slot] = Some(v);
+    }
+
+    /// Looks `k` up by name starting `depth` levels up.
+    pub fn get_from(&self, depth: usize, k: &str) -> Option<Object> {
+        match (depth, &self.outer) {
+            (0, _) => self.get(k),
+            (_, Some(outer)) => outer.borrow().get_from(depth - 1, k),
+            (_, None) => None,
+        }
+    }
+
     pub fn set(&mut self, k: &str, v: Object) -> Option<Object> {
         self.store.insert(k.to_string(), v)
     }
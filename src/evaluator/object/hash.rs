@@ -1,14 +1,20 @@
 use super::super::error::Error;
 use super::Object;
 
+use indexmap::IndexMap;
 use std::convert::TryFrom;
-use std::{collections::HashMap, fmt::Display};
+use std::fmt::Display;
 
+/// The values a hash can be keyed by, compared and hashed structurally.
+/// Keys are copies of the objects used to build them, so they can't change
+/// while stored in a hash.
 #[derive(Debug, Eq, PartialEq, Clone, Hash)]
 pub enum HashableObject {
     Integer(i64),
     Boolean(bool),
     Str(String),
+    Null,
+    Array(Vec<HashableObject>),
 }
 
 impl TryFrom<&Object> for HashableObject {
@@ -18,6 +24,10 @@ impl TryFrom<&Object> for HashableObject {
             Object::Integer(i) => Ok(Self::Integer(*i)),
             Object::Boolean(b) => Ok(Self::Boolean(*b)),
             Object::Str(s) => Ok(Self::Str(s.clone())),
+            Object::Null => Ok(Self::Null),
+            Object::Array(a) => Ok(Self::Array(
+                a.iter().map(Self::try_from).collect::<Result<_, _>>()?,
+            )),
             o => Err(Error::HashError(o.into())),
         }
     }
@@ -29,8 +39,15 @@ impl Display for HashableObject {
             Self::Integer(i) => write!(f, "{i}"),
             Self::Boolean(b) => write!(f, "{b}"),
             Self::Str(s) => write!(f, "\"{s}\""),
+            Self::Null => write!(f, "null"),
+            Self::Array(a) => {
+                let elems = a.iter().map(|k| k.to_string()).collect::<Vec<_>>();
+
+                write!(f, "[{}]", elems.join(", "))
+            }
         }
     }
 }
 
-pub type Hash = HashMap<HashableObject, Object>;
+/// Keeps keys in insertion order, equality still ignores the order.
+pub type Hash = IndexMap<HashableObject, Object>;
@@ -0,0 +1,87 @@
+use super::super::context::Context;
+use super::super::error::{Error, Result};
+use super::Object;
+
+use std::fmt;
+use std::rc::Rc;
+
+pub type NativeFn = dyn Fn(&[Object], &mut Context) -> Result<Object>;
+
+/// How many arguments a native function accepts.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Arity {
+    Exact(usize),
+    AtLeast(usize),
+}
+
+impl Arity {
+    fn check(&self, received: usize) -> Result<()> {
+        match *self {
+            Self::Exact(expected) if received != expected => {
+                Err(Error::ArgumentsError { expected, received })
+            }
+            Self::AtLeast(minimum) if received < minimum => {
+                Err(Error::TooFewArgumentsError { minimum, received })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// A Rust function registered by the host, callable from scripts like a builtin.
+#[derive(Clone)]
+pub struct Native {
+    pub name: String,
+    pub arity: Arity,
+    func: Rc<NativeFn>,
+}
+
+impl Native {
+    pub fn new(
+        name: &str,
+        arity: Arity,
+        func: impl Fn(&[Object], &mut Context) -> Result<Object> + 'static,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            arity,
+            func: Rc::new(func),
+        }
+    }
+
+    pub fn call(&self, args: &[Object], ctx: &mut Context) -> Result<Object> {
+        self.arity.check(args.len())?;
+
+        (self.func)(args, ctx)
+    }
+}
+
+impl fmt::Debug for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Native")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Native {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && Rc::ptr_eq(&self.func, &other.func)
+    }
+}
+
+impl fmt::Display for Native {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let params = match self.arity {
+            Arity::Exact(n) => vec!["_"; n].join(", "),
+            Arity::AtLeast(n) => vec!["_"; n]
+                .into_iter()
+                .chain(["..."])
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+
+        write!(f, "fn {}({params}) {{ NATIVE }}", self.name)
+    }
+}
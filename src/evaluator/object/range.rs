@@ -0,0 +1,114 @@
+use super::super::error::{Error, Result};
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Range {
+    pub start: Option<i64>,
+    pub end: Option<i64>,
+    pub inclusive: bool,
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(start) = self.start {
+            write!(f, "{start}")?;
+        }
+
+        write!(f, "{}", if self.inclusive { "..=" } else { ".." })?;
+
+        if let Some(end) = self.end {
+            write!(f, "{end}")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Range {
+    /// Iterates over the integers in the range, both bounds are required.
+    pub fn iter(&self) -> Result<std::ops::Range<i64>> {
+        match (self.start, self.end) {
+            (Some(start), Some(end)) if self.inclusive => match end.checked_add(1) {
+                Some(end) => Ok(start..end),
+                None => Err(self.too_large()),
+            },
+            (Some(start), Some(end)) => Ok(start..end),
+            _ => Err(Error::RangeError(self.clone())),
+        }
+    }
+
+    pub fn len(&self) -> Result<usize> {
+        let range = self.iter()?;
+
+        if range.is_empty() {
+            return Ok(0);
+        }
+
+        match range.end.checked_sub(range.start) {
+            Some(len) => Ok(len as usize),
+            None => Err(self.too_large()),
+        }
+    }
+
+    /// Ranges with more integers than an `i64` holds can't be iterated.
+    fn too_large(&self) -> Error {
+        Error::ValueError(format!("Range {self} is too large"))
+    }
+
+    pub fn is_empty(&self) -> Result<bool> {
+        self.len().map(|l| l == 0)
+    }
+
+    pub fn get(&self, index: i64) -> Result<i64> {
+        let index = checked_index(index, self.len()?)?;
+
+        Ok(self.iter()?.start + index as i64)
+    }
+
+    /// Converts the range into `start..end` indices of a sequence of length `len`.
+    /// Missing bounds span the whole sequence and negative ones count from the end.
+    pub fn slice_bounds(&self, len: usize) -> Result<(usize, usize)> {
+        let start = match self.start {
+            Some(i) => normalize_index(i, len),
+            None => 0,
+        };
+
+        let end = match self.end {
+            Some(i) if self.inclusive => normalize_index(i, len)
+                .checked_add(1)
+                .ok_or(Error::IndexError(i))?,
+            Some(i) => normalize_index(i, len),
+            None => len as i64,
+        };
+
+        if start < 0 || start > len as i64 {
+            return Err(Error::IndexError(start));
+        }
+
+        if end < start || end > len as i64 {
+            return Err(Error::IndexError(end));
+        }
+
+        Ok((start as usize, end as usize))
+    }
+}
+
+/// Negative indices count backwards from the end of the sequence.
+pub fn normalize_index(index: i64, len: usize) -> i64 {
+    if index < 0 {
+        (len as i64) + index
+    } else {
+        index
+    }
+}
+
+/// Normalizes `index` and checks it points inside a sequence of length `len`.
+pub fn checked_index(index: i64, len: usize) -> Result<usize> {
+    let index = normalize_index(index, len);
+
+    match usize::try_from(index) {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(Error::IndexError(index)),
+    }
+}
@@ -0,0 +1,152 @@
+//! Serde support for objects, enabled by the `serde` feature.
+//!
+//! Integer, boolean and string hash keys keep their own type when
+//! serialized, formats that only allow string keys (like JSON) turn them into
+//! strings. `null` and array keys are written as their display form, as few
+//! formats accept them. All of those are read back as strings.
+
+use super::hash::{Hash, HashableObject};
+use super::{Array, Object, ObjectType};
+
+use ::serde::de::{self, Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+use ::serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use std::fmt;
+
+impl Serialize for Object {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Integer(i) => serializer.serialize_i64(*i),
+            Self::Boolean(b) => serializer.serialize_bool(*b),
+            Self::Null => serializer.serialize_unit(),
+            Self::Str(s) => serializer.serialize_str(s),
+            Self::Array(a) => {
+                let mut seq = serializer.serialize_seq(Some(a.len()))?;
+                for o in a {
+                    seq.serialize_element(o)?;
+                }
+                seq.end()
+            }
+            Self::Hash(h) => {
+                let mut map = serializer.serialize_map(Some(h.len()))?;
+                for (k, v) in h.iter() {
+                    match k {
+                        HashableObject::Null | HashableObject::Array(_) => {
+                            map.serialize_entry(&k.to_string(), v)?
+                        }
+                        k => map.serialize_entry(k, v)?,
+                    }
+                }
+                map.end()
+            }
+            o => Err(ser::Error::custom(format!(
+                "Object of type {} can't be serialized",
+                ObjectType::from(o)
+            ))),
+        }
+    }
+}
+
+impl Serialize for HashableObject {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Integer(i) => serializer.serialize_i64(*i),
+            Self::Boolean(b) => serializer.serialize_bool(*b),
+            Self::Str(s) => serializer.serialize_str(s),
+            Self::Null => serializer.serialize_unit(),
+            Self::Array(a) => {
+                let mut seq = serializer.serialize_seq(Some(a.len()))?;
+                for k in a {
+                    seq.serialize_element(k)?;
+                }
+                seq.end()
+            }
+        }
+    }
+}
+
+struct ObjectVisitor;
+
+impl<'de> Visitor<'de> for ObjectVisitor {
+    type Value = Object;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "an integer, boolean, string, null, array or map")
+    }
+
+    fn visit_bool<E: de::Error>(self, v: bool) -> Result<Object, E> {
+        Ok(Object::Boolean(v))
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Object, E> {
+        Ok(Object::Integer(v))
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Object, E> {
+        i64::try_from(v)
+            .map(Object::Integer)
+            .map_err(|_| E::custom(format!("integer {v} is too large")))
+    }
+
+    /// Only integral numbers can be represented, there are no floats.
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<Object, E> {
+        if v.fract() == 0.0 && v >= i64::MIN as f64 && v <= i64::MAX as f64 {
+            Ok(Object::Integer(v as i64))
+        } else {
+            Err(E::custom(format!("number {v} is not an integer")))
+        }
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Object, E> {
+        Ok(Object::Str(v.into()))
+    }
+
+    fn visit_string<E: de::Error>(self, v: String) -> Result<Object, E> {
+        Ok(Object::Str(v))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Object, E> {
+        Ok(Object::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Object, D::Error> {
+        Object::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Object, A::Error> {
+        let mut array = Array::new();
+
+        while let Some(o) = seq.next_element()? {
+            array.push_back(o);
+        }
+
+        Ok(Object::Array(array))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Object, A::Error> {
+        let mut hash = Hash::with_capacity(map.size_hint().unwrap_or(0));
+
+        while let Some((k, v)) = map.next_entry()? {
+            hash.insert(k, v);
+        }
+
+        Ok(Object::Hash(hash.into()))
+    }
+}
+
+impl<'de> Deserialize<'de> for Object {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ObjectVisitor)
+    }
+}
+
+impl<'de> Deserialize<'de> for HashableObject {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let object = deserializer.deserialize_any(ObjectVisitor)?;
+
+        HashableObject::try_from(&object).map_err(de::Error::custom)
+    }
+}
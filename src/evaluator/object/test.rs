@@ -0,0 +1,237 @@
+use super::collector;
+use super::hash::{Hash, HashableObject};
+use super::native::Arity;
+use super::{Object, ObjectType};
+use crate::evaluator::context::OutputBuffer;
+use crate::evaluator::error::Error;
+use crate::hash_struct;
+use crate::interpreter::Interpreter;
+
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, PartialEq)]
+struct User {
+    name: String,
+    age: i64,
+    admin: bool,
+    tags: Vec<String>,
+    manager: Option<String>,
+}
+
+hash_struct!(User {
+    name,
+    age,
+    admin,
+    tags,
+    manager,
+});
+
+#[test]
+fn test_object_from_rust() {
+    assert_eq!(Object::from(5), Object::Integer(5));
+    assert_eq!(Object::from(true), Object::Boolean(true));
+    assert_eq!(Object::from("joe"), Object::Str("joe".into()));
+    assert_eq!(Object::from(String::from("joe")), Object::Str("joe".into()));
+    assert_eq!(Object::from(()), Object::Null);
+    assert_eq!(Object::from(None::<i64>), Object::Null);
+    assert_eq!(Object::from(Some(1)), Object::Integer(1));
+    assert_eq!(
+        Object::from(vec![1, 2]),
+        Object::Array(vec![Object::Integer(1), Object::Integer(2)].into())
+    );
+    assert_eq!(
+        Object::from((1, "a", false)),
+        Object::Array(
+            vec![
+                Object::Integer(1),
+                Object::Str("a".into()),
+                Object::Boolean(false)
+            ]
+            .into()
+        )
+    );
+    assert_eq!(
+        Object::from(HashMap::from([("a", 1)])),
+        Object::Hash(Hash::from([(HashableObject::Str("a".into()), Object::Integer(1))]).into())
+    );
+}
+
+#[test]
+fn test_object_into_rust() {
+    assert_eq!(i64::try_from(Object::Integer(5)), Ok(5));
+    assert_eq!(bool::try_from(Object::Boolean(true)), Ok(true));
+    assert_eq!(String::try_from(Object::Str("a".into())), Ok("a".into()));
+    assert_eq!(Option::<i64>::try_from(Object::Null), Ok(None));
+    assert_eq!(Option::<i64>::try_from(Object::Integer(2)), Ok(Some(2)));
+    assert_eq!(
+        Vec::<i64>::try_from(Object::from(vec![1, 2, 3])),
+        Ok(vec![1, 2, 3])
+    );
+    assert_eq!(
+        <(i64, String)>::try_from(Object::from((1, "a"))),
+        Ok((1, "a".into()))
+    );
+    assert_eq!(
+        HashMap::<String, Vec<bool>>::try_from(Object::from(HashMap::from([("a", vec![true])]))),
+        Ok(HashMap::from([("a".into(), vec![true])]))
+    );
+
+    assert_eq!(
+        i64::try_from(Object::Str("a".into())),
+        Err(Error::TypeError {
+            expected: ObjectType::Integer,
+            received: ObjectType::Str,
+        })
+    );
+    assert_eq!(
+        Vec::<i64>::try_from(Object::from(vec![Object::Integer(1), Object::Null])),
+        Err(Error::TypeError {
+            expected: ObjectType::Integer,
+            received: ObjectType::Null,
+        })
+    );
+    assert_eq!(
+        <(i64, i64)>::try_from(Object::from(vec![1])),
+        Err(Error::ValueError(
+            "Expected an array of length 2, received one of length 1".into()
+        ))
+    );
+}
+
+#[test]
+fn test_object_struct_round_trip() {
+    let user = User {
+        name: "joe".into(),
+        age: 42,
+        admin: false,
+        tags: vec!["mama".into()],
+        manager: None,
+    };
+
+    let object = Object::from(User {
+        name: "joe".into(),
+        age: 42,
+        admin: false,
+        tags: vec!["mama".into()],
+        manager: None,
+    });
+
+    match &object {
+        Object::Hash(h) => {
+            assert_eq!(
+                h.get(&HashableObject::from("age")),
+                Some(&Object::Integer(42))
+            );
+            assert_eq!(h.get(&HashableObject::from("manager")), Some(&Object::Null));
+        }
+        o => panic!("Hash expected, received {o:?}"),
+    }
+
+    assert_eq!(User::try_from(object), Ok(user));
+
+    // Missing keys read as null
+    let partial = Object::from(HashMap::from([("name", "joe")]));
+
+    assert_eq!(
+        User::try_from(partial),
+        Err(Error::TypeError {
+            expected: ObjectType::Integer,
+            received: ObjectType::Null,
+        })
+    );
+    assert_eq!(
+        User::try_from(Object::Integer(1)),
+        Err(Error::TypeError {
+            expected: ObjectType::Hash,
+            received: ObjectType::Integer,
+        })
+    );
+}
+
+#[test]
+fn test_collector_frees_cycles() {
+    let mut interpreter = Interpreter::new();
+
+    let closures = [
+        "let f = fn() { f }; f",
+        "let a = [1, 2]; let f = fn() { [a, f] }; f",
+        r#"let h = hash!{"f": fn() { h }}; h["f"]"#,
+    ];
+
+    for closure in closures {
+        let input = format!("let make = fn() {{ {closure} }}; let g = make();");
+        assert_eq!(interpreter.eval(&input), Ok(Object::Null));
+
+        let env = match interpreter.env().borrow().get("g") {
+            Some(Object::Function(f)) => Rc::downgrade(&f.env),
+            x => panic!("Function expected, received {x:?}"),
+        };
+
+        assert_eq!(interpreter.eval("let g = 0;"), Ok(Object::Null));
+
+        collector::collect();
+
+        assert!(env.upgrade().is_none(), "Failed closure: {closure}");
+    }
+}
+
+#[test]
+fn test_collector_keeps_live_environments() {
+    let mut interpreter = Interpreter::new();
+    interpreter.register("gc", Arity::Exact(0), |_, _| {
+        Ok(Object::Integer(collector::collect() as i64))
+    });
+
+    let input = "
+        let counter = fn() {
+            let count = fn(n) { if (n == 0) { gc(); 0 } else { 1 + count(n - 1) } };
+            count
+        };
+        let c = counter();
+    ";
+    assert_eq!(interpreter.eval(input), Ok(Object::Null));
+    assert_eq!(interpreter.eval("c(10)"), Ok(Object::Integer(10)));
+
+    // Only referenced from Rust
+    let held = interpreter.eval("counter()").unwrap();
+    collector::collect();
+    interpreter.env().borrow_mut().set("held", held);
+    assert_eq!(interpreter.eval("held(5) + c(3)"), Ok(Object::Integer(8)));
+}
+
+#[test]
+fn test_collector_keeps_array_elements() {
+    let stdout = OutputBuffer::new();
+    let mut interpreter = Interpreter::builder().stdout(stdout.clone()).build();
+
+    // The array returned by make is only held by use's arguments while burn
+    // creates enough environments to start a collection
+    let input = "
+        let make = fn() { let x = 5; let g = fn() { x }; let arr = [g]; arr };
+        let burn = fn(n) { if (n > 0) { burn(n - 1) } else { 0 } };
+        let use = fn(a, z) { a[0]() };
+        puts(use(make(), burn(3000)));
+    ";
+    assert_eq!(interpreter.eval(input), Ok(Object::Null));
+    assert_eq!(stdout.contents(), "5\n");
+}
+
+#[test]
+fn test_collector_bounds_environments() {
+    let mut interpreter = Interpreter::new();
+    collector::collect();
+    let before = collector::tracked();
+
+    let input = "
+        let spin = fn(n) { if (n > 0) { let f = fn() { f }; spin(n - 1) } };
+        spin(3000);
+    ";
+    assert_eq!(interpreter.eval(input), Ok(Object::Null));
+
+    // Collected automatically while running, each iteration leaks two
+    assert!(collector::tracked() < before + 3000);
+
+    collector::collect();
+    assert_eq!(collector::tracked(), before);
+}
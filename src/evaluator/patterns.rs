@@ -0,0 +1,92 @@
+//! Regular expressions used by the `re_*` builtins.
+
+use super::error::{Error, Result};
+use super::object::hash::{Hash, HashableObject};
+use super::object::{Array, Object};
+
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::rc::Rc;
+
+/// Compiled patterns kept at most, the cache is emptied once full.
+const MAX_PATTERNS: usize = 128;
+
+/// Patterns compiled during the lifetime of a context, by source.
+#[derive(Debug, Default)]
+pub struct Patterns {
+    cache: HashMap<String, Regex>,
+}
+
+impl Patterns {
+    pub fn compile(&mut self, pattern: &str) -> Result<Regex> {
+        if let Some(regex) = self.cache.get(pattern) {
+            return Ok(regex.clone());
+        }
+
+        let regex = Regex::new(pattern).map_err(|e| Error::RegexError {
+            pattern: pattern.into(),
+            reason: e.to_string(),
+        })?;
+
+        if self.cache.len() >= MAX_PATTERNS {
+            self.cache.clear();
+        }
+        self.cache.insert(pattern.into(), regex.clone());
+
+        Ok(regex)
+    }
+}
+
+/// Converts increasing byte offsets in a string to character indexes, which
+/// is how scripts index strings.
+pub struct CharIndexes<'a> {
+    haystack: &'a str,
+    byte: usize,
+    chars: usize,
+}
+
+impl<'a> CharIndexes<'a> {
+    pub fn new(haystack: &'a str) -> Self {
+        Self {
+            haystack,
+            byte: 0,
+            chars: 0,
+        }
+    }
+
+    fn at(&mut self, byte: usize) -> i64 {
+        self.chars += self.haystack[self.byte..byte].chars().count();
+        self.byte = byte;
+
+        self.chars as i64
+    }
+}
+
+/// A hash with the text of a match, where it starts and ends, and its groups
+/// by position and by name. Groups that didn't participate are `null`.
+pub fn match_object(regex: &Regex, captures: &Captures, indexes: &mut CharIndexes) -> Object {
+    let whole = captures.get(0).expect("Group 0 is the whole match");
+    let text = |group: Option<regex::Match>| group.map_or(Object::Null, |g| g.as_str().into());
+
+    let groups: Array = captures.iter().skip(1).map(text).collect();
+    let named: Hash = regex
+        .capture_names()
+        .flatten()
+        .map(|name| (HashableObject::Str(name.into()), text(captures.name(name))))
+        .collect();
+
+    let fields = [
+        ("match", Object::from(whole.as_str())),
+        ("start", Object::Integer(indexes.at(whole.start()))),
+        ("end", Object::Integer(indexes.at(whole.end()))),
+        ("groups", Object::Array(groups)),
+        ("named", Object::Hash(Rc::new(named))),
+    ];
+
+    Object::Hash(Rc::new(
+        fields
+            .into_iter()
+            .map(|(k, v)| (HashableObject::Str(k.into()), v))
+            .collect(),
+    ))
+}
@@ -0,0 +1,34 @@
+//! Standard library written in Monkey, evaluated into the builtins
+//! environment so every script and module can use it.
+
+use super::context::Context;
+use super::object::builtins::Builtin;
+use super::object::environment::HeapEnvironment;
+use super::Evaluate;
+use crate::parser::ast::statements::Statement;
+use crate::parser::Parse;
+use crate::resolver;
+
+use std::sync::LazyLock;
+
+const SOURCE: &str = include_str!("prelude.monkey");
+
+/// The prelude parsed on first use, resolved against the builtins.
+static PRELUDE: LazyLock<Vec<Statement>> = LazyLock::new(|| {
+    let mut stmts: Vec<_> = SOURCE
+        .parser()
+        .collect::<Result<_, _>>()
+        .expect("prelude should parse");
+
+    resolver::resolve(&mut stmts, &Builtin::environment()).expect("prelude should resolve");
+
+    stmts
+});
+
+/// Defines the prelude in `env`, an environment made by
+/// `Builtin::environment`.
+pub fn load(env: &HeapEnvironment) {
+    PRELUDE
+        .eval(env.clone(), &mut Context::new())
+        .expect("prelude should evaluate");
+}
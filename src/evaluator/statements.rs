@@ -1,51 +1,94 @@
+use super::context::Context;
 use super::error::{Error, Result};
 use super::object::environment::HeapEnvironment;
-use super::object::Object;
-use super::Evaluate;
+use super::object::{FunctionObject, Object};
+use super::{Evaluate, EvaluateTail, Tail};
 use crate::parser::ast::statements as stmt;
 
 impl Evaluate for stmt::Statement {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
         match self {
-            Self::Expression(e) => e.eval(env),
-            Self::Return(r) => r.eval(env),
-            Self::Let(l) => l.eval(env),
+            Self::Expression(e) => e.eval(env, ctx),
+            Self::Return(r) => r.eval(env, ctx),
+            Self::Throw(t) => t.eval(env, ctx),
+            Self::Let(l) | Self::Export(l) => l.eval(env, ctx),
+            Self::Import(i) => i.eval(env, ctx),
             _ => Ok(Object::Null),
         }
     }
 }
 
+impl EvaluateTail for stmt::Statement {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        match self {
+            Self::Expression(e) => e.expression.eval_tail(env, ctx),
+            // Returning a call's value is the same as ending with the call
+            Self::Return(r) => match r.return_value.eval_tail(env, ctx)? {
+                Tail::Value(o) => Err(Error::Return(o)),
+                call => Ok(call),
+            },
+            s => s.eval(env, ctx).map(Tail::Value),
+        }
+    }
+}
+
 impl Evaluate for Vec<stmt::Statement> {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
         let mut result = Object::Null;
 
         for s in self {
-            result = s.eval(env.clone())?;
+            result = s.eval(env.clone(), ctx)?;
         }
 
         Ok(result)
     }
 }
 
+impl EvaluateTail for Vec<stmt::Statement> {
+    fn eval_tail(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Tail> {
+        let Some((last, init)) = self.split_last() else {
+            return Ok(Tail::Value(Object::Null));
+        };
+
+        for s in init {
+            s.eval(env.clone(), ctx)?;
+        }
+
+        last.eval_tail(env, ctx)
+    }
+}
+
 impl Evaluate for stmt::ExpressionStmt {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        self.expression.eval(env)
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        self.expression.eval(env, ctx)
     }
 }
 
 impl Evaluate for stmt::Return {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let return_value = self.return_value.eval(env)?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let return_value = self.return_value.eval(env, ctx)?;
         // We bubble up returns with errors
         Err(Error::Return(return_value))
     }
 }
 
+impl Evaluate for stmt::Throw {
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let value = self.value.eval(env, ctx)?;
+
+        Err(Error::Thrown(value))
+    }
+}
+
 impl Evaluate for stmt::Let {
-    fn eval(&self, env: HeapEnvironment) -> Result<Object> {
-        let expression = self.value.eval(env.clone())?;
+    fn eval(&self, env: HeapEnvironment, ctx: &mut Context) -> Result<Object> {
+        let mut expression = self.value.eval(env.clone(), ctx)?;
+
+        if let Object::Function(f @ FunctionObject { name: None, .. }) = &mut expression {
+            f.name = Some(self.name.value.to_string());
+        }
 
-        env.borrow_mut().set(&self.name.value, expression);
+        self.name.bind(&env, expression);
         Ok(Object::Null)
     }
 }
@@ -1,10 +1,23 @@
-use super::object::{environment::Environment, hash::HashableObject, Object, ObjectType};
-use super::{error::Error, Evaluate};
+use super::object::{
+    environment::Environment,
+    hash::{Hash, HashableObject},
+    range::Range,
+    Object, ObjectType,
+};
+use super::{
+    context::{CallSite, Context, Frame},
+    error::{error_object, Error},
+    macros, Evaluate,
+};
+use crate::lexer::token::Location;
 use crate::parser::ast::expressions::{InfixOp, PrefixOp};
+use crate::parser::ast::statements::Statement;
 use crate::parser::error::Error as ParserError;
 use crate::parser::Parse;
+use crate::resolver;
 
-use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
 
 struct EvalTest {
     pub input: &'static str,
@@ -16,6 +29,26 @@ struct EvalErrorTest {
     pub expected: Error,
 }
 
+/// Checks how the result is displayed, for objects that can't be compared
+struct EvalDisplayTest {
+    pub input: &'static str,
+    pub expected: &'static str,
+}
+
+fn test_eval_display(test: EvalDisplayTest) {
+    let mut stmts: Vec<_> = test.input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+
+    if let Err(e) = resolver::resolve(&mut stmts, &env) {
+        panic!("Error resolving: {e}");
+    }
+
+    match stmts.eval_return(env, &mut Context::new()) {
+        Ok(x) => assert_eq!(x.to_string(), test.expected, "Failed input: {}", test.input),
+        Err(e) => panic!("Error evaluating: {e}"),
+    }
+}
+
 fn test_eval_output(test: EvalTest) {
     let parser = test.input.parser();
 
@@ -24,8 +57,12 @@ fn test_eval_output(test: EvalTest) {
     let env = Environment::new_heap(None);
 
     match parse_result {
-        Ok(stmts) => {
-            match stmts.eval_return(env) {
+        Ok(mut stmts) => {
+            if let Err(e) = resolver::resolve(&mut stmts, &env) {
+                panic!("Error resolving: {e}");
+            }
+
+            match stmts.eval_return(env, &mut Context::new()) {
                 Ok(x) => assert_eq!(x, test.expected, "Failed input: {}", test.input),
                 Err(e) => panic!("Error evaluating: {e}"),
             };
@@ -34,6 +71,27 @@ fn test_eval_output(test: EvalTest) {
     }
 }
 
+fn test_eval_error(test: EvalErrorTest) {
+    let parser = test.input.parser();
+
+    let parse_result: Result<Vec<_>, ParserError> = parser.collect();
+
+    let env = Environment::new_heap(None);
+
+    match parse_result {
+        Ok(mut stmts) => {
+            // Unresolved identifiers are still reported when evaluated
+            let _ = resolver::resolve(&mut stmts, &env);
+
+            match stmts.eval_return(env, &mut Context::new()) {
+                Ok(_) => panic!("Input '{}' was expected to error", test.input),
+                Err(e) => assert_eq!(e, test.expected),
+            };
+        }
+        Err(e) => panic!("Error parsing: {e}"),
+    }
+}
+
 #[test]
 fn test_eval_literals() {
     let tests = vec![
@@ -331,6 +389,38 @@ fn test_eval_funcs() {
               addTwo(2);"#,
             expected: Object::Integer(4),
         },
+        EvalTest {
+            input: "let fact = fn(n) { if (n == 0) { 1 } else { n * fact(n - 1) } }; fact(5)",
+            expected: Object::Integer(120),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+/// Each call binds its parameters in a new environment on top of the
+/// closure's, instead of in the closure's environment itself.
+#[test]
+fn test_eval_call_scopes() {
+    let tests = vec![
+        EvalTest {
+            input: "let f = fn(n) { if (n == 0) { 0 } else { let r = f(n - 1); n + r } }; f(3)",
+            expected: Object::Integer(6),
+        },
+        EvalTest {
+            input: "let x = 1; let f = fn(x) { x }; f(2); x",
+            expected: Object::Integer(1),
+        },
+        EvalTest {
+            input: "let f = fn() { let y = 1; y }; f(); let y = 2; y",
+            expected: Object::Integer(2),
+        },
+        EvalTest {
+            input: "let adder = fn(n) { fn(x) { x + n } }; let one = adder(1); adder(5); one(10)",
+            expected: Object::Integer(11),
+        },
     ];
 
     for test in tests {
@@ -357,11 +447,368 @@ fn test_eval_builtin_funcs() {
             input: "let a = 9; del(\"a\")",
             expected: Object::Integer(9),
         },
+        EvalTest {
+            input: "let a = 9; let f = fn() { del(\"a\") }; f()",
+            expected: Object::Integer(9),
+        },
+        EvalTest {
+            input: "let f = fn(n) { let x = 1; del(n); x }; try { f(\"x\") } catch (e) { e[\"kind\"] }",
+            expected: Object::Str("ValueError".into()),
+        },
+        EvalTest {
+            input: "let f = fn() { let x = 1; let d = del; d(\"x\"); x }; try { f() } catch (e) { e[\"kind\"] }",
+            expected: Object::Str("ValueError".into()),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_higher_order_builtins() {
+    let tests = vec![
+        EvalDisplayTest {
+            input: "map([1, 2, 3], fn(x) { x * 2 })",
+            expected: "[2, 4, 6]",
+        },
+        EvalDisplayTest {
+            input: "map(1..4, fn(x) { x * x })",
+            expected: "[1, 4, 9]",
+        },
+        EvalDisplayTest {
+            input: r#"map(hash!{"a": 1, "b": 2}, fn(k, v) { v * 10 })"#,
+            expected: r#"{"a": 10, "b": 20}"#,
+        },
+        EvalDisplayTest {
+            input: "filter([1, 2, 3, 4], fn(x) { x > 2 })",
+            expected: "[3, 4]",
+        },
+        EvalDisplayTest {
+            input: r#"filter(hash!{"a": 1, "b": 2}, fn(k, v) { v > 1 })"#,
+            expected: r#"{"b": 2}"#,
+        },
+        EvalDisplayTest {
+            input: "reduce([1, 2, 3], 10, fn(acc, x) { acc + x })",
+            expected: "16",
+        },
+        EvalDisplayTest {
+            input: r#"reduce(hash!{"a": 1, "b": 2}, "", fn(acc, k, v) { acc + k })"#,
+            expected: "ab",
+        },
+        EvalDisplayTest {
+            input: "reduce([], 0, fn(acc, x) { acc + x })",
+            expected: "0",
+        },
+        EvalDisplayTest {
+            input: "sort([3, 1, 2])",
+            expected: "[1, 2, 3]",
+        },
+        EvalDisplayTest {
+            input: r#"sort(["b", "c", "a"])"#,
+            expected: "[a, b, c]",
+        },
+        EvalDisplayTest {
+            input: "sort([[2, 1], [1, 2], [1]])",
+            expected: "[[1], [1, 2], [2, 1]]",
+        },
+        EvalDisplayTest {
+            input: "sort([])",
+            expected: "[]",
+        },
+        EvalDisplayTest {
+            input: r#"sort_by(["ccc", "a", "bb"], len)"#,
+            expected: "[a, bb, ccc]",
+        },
+        EvalDisplayTest {
+            input: "sort_by([3, 1, 2], fn(x) { -x })",
+            expected: "[3, 2, 1]",
+        },
+        EvalDisplayTest {
+            input: r#"sort_by(hash!{"a": 2, "b": 1}, fn(k, v) { v })"#,
+            expected: r#"{"b": 1, "a": 2}"#,
+        },
+        EvalDisplayTest {
+            input: "any([1, 2, 3], fn(x) { x > 2 })",
+            expected: "true",
+        },
+        EvalDisplayTest {
+            input: "any([], fn(x) { true })",
+            expected: "false",
+        },
+        EvalDisplayTest {
+            input: "all([1, 2, 3], fn(x) { x > 0 })",
+            expected: "true",
+        },
+        EvalDisplayTest {
+            input: "all([1, 2, 3], fn(x) { x > 1 })",
+            expected: "false",
+        },
+        EvalDisplayTest {
+            input: r#"all(hash!{"a": 1}, fn(k, v) { k == "a" })"#,
+            expected: "true",
+        },
+        EvalDisplayTest {
+            input: "find([1, 2, 3, 4], fn(x) { x > 2 })",
+            expected: "3",
+        },
+        EvalDisplayTest {
+            input: "find([1, 2], fn(x) { x > 2 })",
+            expected: "null",
+        },
+        EvalDisplayTest {
+            input: r#"find(hash!{"a": 1, "b": 2}, fn(k, v) { v == 2 })"#,
+            expected: "[b, 2]",
+        },
+        EvalDisplayTest {
+            input: "find(0..10000000000, fn(x) { x * x > 10 })",
+            expected: "4",
+        },
+        EvalDisplayTest {
+            input: "all(0..10000000000, fn(x) { x < 3 })",
+            expected: "false",
+        },
+        EvalDisplayTest {
+            input: r#"zip([1, 2, 3], ["a", "b"])"#,
+            expected: "[[1, a], [2, b]]",
+        },
+        EvalDisplayTest {
+            input: r#"enumerate(["a", "b"])"#,
+            expected: "[[0, a], [1, b]]",
+        },
+        EvalDisplayTest {
+            input: "let count = fn(n) { len(filter(0..n, fn(x) { x < n })) }; map([1, 2], count)",
+            expected: "[1, 2]",
+        },
+    ];
+
+    for test in tests {
+        test_eval_display(test)
+    }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: "map(1, fn(x) { x })",
+            expected: Error::TypeError {
+                expected: ObjectType::Array,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: "map([1], 2)",
+            expected: Error::CallableError(ObjectType::Integer),
+        },
+        EvalErrorTest {
+            input: "filter([1], fn(x, y) { x })",
+            expected: Error::ArgumentsError {
+                expected: 2,
+                received: 1,
+            },
+        },
+        EvalErrorTest {
+            input: "reduce([1], fn(x) { x })",
+            expected: Error::ArgumentsError {
+                expected: 3,
+                received: 2,
+            },
+        },
+        EvalErrorTest {
+            input: r#"sort([1, "a"])"#,
+            expected: Error::TypeError {
+                expected: ObjectType::Integer,
+                received: ObjectType::Str,
+            },
+        },
+        EvalErrorTest {
+            input: "sort([fn() { 1 }])",
+            expected: Error::TypeError {
+                expected: ObjectType::Integer,
+                received: ObjectType::Function,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
+}
+
+#[test]
+fn test_eval_callback_traceback() {
+    let input = "let double = fn(x) { x * true };\nmap([1], double)";
+    let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+    resolver::resolve(&mut stmts, &env).unwrap();
+
+    let error = stmts
+        .eval_return(env, &mut Context::new())
+        .expect_err("Input was expected to error");
+
+    assert_eq!(
+        error.traceback().expect("Traceback expected").0,
+        vec![Frame {
+            function: Some("double".into()),
+            call: CallSite::Callback("map"),
+            location: Location { line: 2, column: 1 },
+        }]
+    );
+}
+
+#[test]
+fn test_eval_type_builtins() {
+    let tests = vec![
+        EvalTest {
+            input: "type(1)",
+            expected: Object::from("INTEGER"),
+        },
+        EvalTest {
+            input: r#"type("a")"#,
+            expected: Object::from("STRING"),
+        },
+        EvalTest {
+            input: "type(hash!{})",
+            expected: Object::from("HASH"),
+        },
+        EvalTest {
+            input: "type(fn() { 1 })",
+            expected: Object::from("FUNCTION"),
+        },
+        EvalTest {
+            input: "type(len)",
+            expected: Object::from("BUILTIN FUNCTION"),
+        },
+        EvalTest {
+            input: "type(if (false) { 1 })",
+            expected: Object::from("NULL"),
+        },
+        EvalTest {
+            input: "str(12)",
+            expected: Object::from("12"),
+        },
+        EvalTest {
+            input: r#"str([1, "a"])"#,
+            expected: Object::from("[1, a]"),
+        },
+        EvalTest {
+            input: r#"str("a")"#,
+            expected: Object::from("a"),
+        },
+        EvalTest {
+            input: r#"int("-42")"#,
+            expected: Object::from(-42),
+        },
+        EvalTest {
+            input: "int(7)",
+            expected: Object::from(7),
+        },
+        EvalTest {
+            input: "int(true)",
+            expected: Object::from(1),
+        },
+        EvalTest {
+            input: "bool(0)",
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: r#"bool("a")"#,
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "bool([])",
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: "is_int(1)",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"is_int("1")"#,
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: "is_bool(false)",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"is_str("")"#,
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_null(if (false) { 1 })",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_null(0)",
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: "is_array([])",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_hash(hash!{})",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_range(1..2)",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_callable(len)",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: "is_callable(fn() { 1 })",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"is_callable("len")"#,
+            expected: Object::from(false),
+        },
     ];
 
     for test in tests {
         test_eval_output(test)
     }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: r#"int("4 2")"#,
+            expected: Error::ValueError(r#"int expects a decimal integer, received "4 2""#.into()),
+        },
+        EvalErrorTest {
+            input: r#"int("99999999999999999999")"#,
+            expected: Error::ValueError(
+                r#"int expects a decimal integer, received "99999999999999999999""#.into(),
+            ),
+        },
+        EvalErrorTest {
+            input: "int([1])",
+            expected: Error::TypeError {
+                expected: ObjectType::Str,
+                received: ObjectType::Array,
+            },
+        },
+        EvalErrorTest {
+            input: "type()",
+            expected: Error::ArgumentsError {
+                expected: 1,
+                received: 0,
+            },
+        },
+        EvalErrorTest {
+            input: "is_int(1, 2)",
+            expected: Error::ArgumentsError {
+                expected: 1,
+                received: 2,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
 }
 
 #[test]
@@ -369,15 +816,30 @@ fn test_eval_arrays() {
     let tests = vec![
         EvalTest {
             input: "[]",
-            expected: Object::Array(vec![]),
+            expected: Object::Array(vec![].into()),
         },
         EvalTest {
             input: "[1]",
-            expected: Object::Array(vec![Object::Integer(1)]),
+            expected: Object::Array(vec![Object::Integer(1)].into()),
         },
         EvalTest {
             input: "[1, true]",
-            expected: Object::Array(vec![Object::Integer(1), Object::Boolean(true)]),
+            expected: Object::Array(vec![Object::Integer(1), Object::Boolean(true)].into()),
+        },
+        EvalTest {
+            input: "let a = [1]; let b = push(a, 2); let c = push(a, 3); [a, b, c]",
+            expected: Object::Array(
+                vec![
+                    Object::Array(vec![Object::Integer(1)].into()),
+                    Object::Array(vec![Object::Integer(1), Object::Integer(2)].into()),
+                    Object::Array(vec![Object::Integer(1), Object::Integer(3)].into()),
+                ]
+                .into(),
+            ),
+        },
+        EvalTest {
+            input: "rest(rest([1]))",
+            expected: Object::Array(vec![].into()),
         },
     ];
 
@@ -386,6 +848,28 @@ fn test_eval_arrays() {
     }
 }
 
+#[test]
+fn test_eval_shared_containers() {
+    let input = "let a = [1, 2, 3]; let h = hash!{1: a};";
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+
+    stmts.eval_return(env.clone(), &mut Context::new()).unwrap();
+
+    // Lookups hand out the same storage instead of deep copies
+    let env = env.borrow();
+
+    match (env.get("a"), env.get("a")) {
+        (Some(Object::Array(x)), Some(Object::Array(y))) => assert!(x.ptr_eq(&y)),
+        x => panic!("Arrays expected, received {x:?}"),
+    }
+
+    match (env.get("h"), env.get("h")) {
+        (Some(Object::Hash(x)), Some(Object::Hash(y))) => assert!(Rc::ptr_eq(&x, &y)),
+        x => panic!("Hashes expected, received {x:?}"),
+    }
+}
+
 #[test]
 fn test_eval_index() {
     let tests = vec![
@@ -453,53 +937,1111 @@ fn test_eval_index() {
 }
 
 #[test]
-fn test_eval_hash() {
+fn test_eval_ranges() {
     let tests = vec![
         EvalTest {
-            input: "hash!{}",
-            expected: Object::Hash(HashMap::new()),
+            input: "1..3",
+            expected: Object::Range(Range {
+                start: Some(1),
+                end: Some(3),
+                inclusive: false,
+            }),
         },
         EvalTest {
-            input: "hash!{1: true}",
-            expected: Object::Hash({
-                let mut h = HashMap::new();
-                h.insert(HashableObject::Integer(1), Object::Boolean(true));
-                h
+            input: "let n = 2; ..=n * 2",
+            expected: Object::Range(Range {
+                start: None,
+                end: Some(4),
+                inclusive: true,
             }),
         },
         EvalTest {
-            input: r#"
-            let two = "two";
-            hash!{
-                "one": 10 - 9,
-                two: 1 + 1,
-                "thr" + "ee": 6 / 2,
-                4: 4,
-                true: 5,
-                false: 6
-            }"#,
-            expected: Object::Hash({
-                let mut h = HashMap::new();
-                h.insert(HashableObject::Str("one".into()), Object::Integer(1));
-                h.insert(HashableObject::Str("two".into()), Object::Integer(2));
-                h.insert(HashableObject::Str("three".into()), Object::Integer(3));
-                h.insert(HashableObject::Integer(4), Object::Integer(4));
-                h.insert(HashableObject::Boolean(true), Object::Integer(5));
-                h.insert(HashableObject::Boolean(false), Object::Integer(6));
-                h
-            }),
+            input: "len(1..3)",
+            expected: Object::Integer(2),
         },
-    ];
-
-    for test in tests {
-        test_eval_output(test)
-    }
-}
-
-#[test]
-fn test_eval_errors() {
-    let tests = vec![
-        EvalErrorTest {
+        EvalTest {
+            input: "len(1..=3)",
+            expected: Object::Integer(3),
+        },
+        EvalTest {
+            input: "len(3..1)",
+            expected: Object::Integer(0),
+        },
+        EvalTest {
+            input: "(5..10)[1]",
+            expected: Object::Integer(6),
+        },
+        EvalTest {
+            input: "(5..10)[-1]",
+            expected: Object::Integer(9),
+        },
+        EvalTest {
+            input: "rest(1..=4)",
+            expected: Object::Array(
+                vec![Object::Integer(2), Object::Integer(3), Object::Integer(4)].into(),
+            ),
+        },
+        EvalTest {
+            input: "if (1..1) { 1 } else { 2 }",
+            expected: Object::Integer(2),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_range_bounds() {
+    let tests = vec![
+        EvalTest {
+            input: "len(0..=9223372036854775806)",
+            expected: Object::Integer(i64::MAX),
+        },
+        EvalTest {
+            input: "len(9223372036854775807..-9223372036854775807)",
+            expected: Object::Integer(0),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_slices() {
+    let tests = vec![
+        EvalTest {
+            input: "[1, 2, 3, 4][1..3]",
+            expected: Object::Array(vec![Object::Integer(2), Object::Integer(3)].into()),
+        },
+        EvalTest {
+            input: "[1, 2, 3, 4][1..=3]",
+            expected: Object::Array(
+                vec![Object::Integer(2), Object::Integer(3), Object::Integer(4)].into(),
+            ),
+        },
+        EvalTest {
+            input: "[1, 2, 3, 4][..-1]",
+            expected: Object::Array(
+                vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)].into(),
+            ),
+        },
+        EvalTest {
+            input: "[1, 2, 3, 4][-2..]",
+            expected: Object::Array(vec![Object::Integer(3), Object::Integer(4)].into()),
+        },
+        EvalTest {
+            input: "[1, 2, 3][..]",
+            expected: Object::Array(
+                vec![Object::Integer(1), Object::Integer(2), Object::Integer(3)].into(),
+            ),
+        },
+        EvalTest {
+            input: "[1, 2, 3][3..]",
+            expected: Object::Array(vec![].into()),
+        },
+        EvalTest {
+            input: r#""joe mama"[4..]"#,
+            expected: Object::Str("mama".into()),
+        },
+        EvalTest {
+            input: r#""joe mama"[..3]"#,
+            expected: Object::Str("joe".into()),
+        },
+        EvalTest {
+            input: r#"let s = "joe mama"; s[1..-1]"#,
+            expected: Object::Str("oe mam".into()),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_str_builtins() {
+    let tests = vec![
+        EvalTest {
+            input: r#"split("a,b,,c", ",")"#,
+            expected: Object::from(vec!["a", "b", "", "c"]),
+        },
+        EvalTest {
+            input: r#"split("  joe   mama ")"#,
+            expected: Object::from(vec!["joe", "mama"]),
+        },
+        EvalTest {
+            input: r#"split("añb", "")"#,
+            expected: Object::from(vec!["a", "ñ", "b"]),
+        },
+        EvalTest {
+            input: r#"join(["a", "b", "c"], ", ")"#,
+            expected: Object::from("a, b, c"),
+        },
+        EvalTest {
+            input: r#"join([], ",")"#,
+            expected: Object::from(""),
+        },
+        EvalTest {
+            input: r#"trim("  joe mama ")"#,
+            expected: Object::from("joe mama"),
+        },
+        EvalTest {
+            input: r#"upper("straße")"#,
+            expected: Object::from("STRASSE"),
+        },
+        EvalTest {
+            input: r#"lower("ÀB")"#,
+            expected: Object::from("àb"),
+        },
+        EvalTest {
+            input: r#"replace("a-b-c", "-", "+")"#,
+            expected: Object::from("a+b+c"),
+        },
+        EvalTest {
+            input: r#"contains("joe mama", "mama")"#,
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"contains("joe", "x")"#,
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: r#"contains([1, "a", [2]], [2])"#,
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"contains([1, 2], "1")"#,
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: "contains(1..5, 4)",
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"starts_with("joe mama", "joe")"#,
+            expected: Object::from(true),
+        },
+        EvalTest {
+            input: r#"ends_with("joe mama", "joe")"#,
+            expected: Object::from(false),
+        },
+        EvalTest {
+            input: r#"index_of("ñañb", "b")"#,
+            expected: Object::from(3),
+        },
+        EvalTest {
+            input: r#"index_of("abc", "x")"#,
+            expected: Object::from(-1),
+        },
+        EvalTest {
+            input: r#"repeat("ab", 3)"#,
+            expected: Object::from("ababab"),
+        },
+        EvalTest {
+            input: r#"repeat("ab", 0)"#,
+            expected: Object::from(""),
+        },
+        EvalTest {
+            input: r#"chars("héllo")"#,
+            expected: Object::from(vec!["h", "é", "l", "l", "o"]),
+        },
+        EvalTest {
+            input: r#"pad_left("7", 3, "0")"#,
+            expected: Object::from("007"),
+        },
+        EvalTest {
+            input: r#"pad_right("ñ", 3)"#,
+            expected: Object::from("ñ  "),
+        },
+        EvalTest {
+            input: r#"pad_left("long", 2)"#,
+            expected: Object::from("long"),
+        },
+        EvalTest {
+            input: r#"format("{} + {} = {}", 1, 2, [3])"#,
+            expected: Object::from("1 + 2 = [3]"),
+        },
+        EvalTest {
+            input: r#"format("{{}} {}", "a")"#,
+            expected: Object::from("{} a"),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: "upper(1)",
+            expected: Error::TypeError {
+                expected: ObjectType::Str,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: r#"join(["a", 1], "")"#,
+            expected: Error::TypeError {
+                expected: ObjectType::Str,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: "split()",
+            expected: Error::ArgumentsError {
+                expected: 2,
+                received: 0,
+            },
+        },
+        EvalErrorTest {
+            input: r#"pad_left("a")"#,
+            expected: Error::ArgumentsError {
+                expected: 3,
+                received: 1,
+            },
+        },
+        EvalErrorTest {
+            input: r#"repeat("a", -1)"#,
+            expected: Error::ValueError("repeat expects a non-negative count, received -1".into()),
+        },
+        EvalErrorTest {
+            input: r#"repeat("ab", 9223372036854775807)"#,
+            expected: Error::ValueError("Result of repeat is too large".into()),
+        },
+        EvalErrorTest {
+            input: r#"pad_left("a", 9223372036854775807, "é")"#,
+            expected: Error::ValueError("Result of pad_left is too large".into()),
+        },
+        EvalErrorTest {
+            input: r#"pad_right("éé", 9223372036854775807)"#,
+            expected: Error::ValueError("Result of pad_right is too large".into()),
+        },
+        EvalErrorTest {
+            input: r#"pad_left("a", 3, "ab")"#,
+            expected: Error::ValueError(
+                r#"pad_left expects a single character to pad with, received "ab""#.into(),
+            ),
+        },
+        EvalErrorTest {
+            input: r#"format("{}")"#,
+            expected: Error::ValueError(
+                "Format template has 1 placeholders, received 0 arguments".into(),
+            ),
+        },
+        EvalErrorTest {
+            input: r#"format("{", 1)"#,
+            expected: Error::ValueError(r#"Unmatched { in format template "{""#.into()),
+        },
+        EvalErrorTest {
+            input: "format()",
+            expected: Error::TooFewArgumentsError {
+                minimum: 1,
+                received: 0,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
+}
+
+#[test]
+fn test_eval_unicode_str() {
+    let tests = vec![
+        EvalTest {
+            input: r#"len("é")"#,
+            expected: Object::Integer(1),
+        },
+        EvalTest {
+            input: r#"len("🙂 joe")"#,
+            expected: Object::Integer(5),
+        },
+        EvalTest {
+            input: r#""joe"[0]"#,
+            expected: Object::Str("j".into()),
+        },
+        EvalTest {
+            input: r#""mañana"[2]"#,
+            expected: Object::Str("ñ".into()),
+        },
+        EvalTest {
+            input: r#""mañana"[-1]"#,
+            expected: Object::Str("a".into()),
+        },
+        EvalTest {
+            input: r#""🙂🙃🙂"[1..]"#,
+            expected: Object::Str("🙃🙂".into()),
+        },
+        EvalTest {
+            input: r#""mañana"[1..=3]"#,
+            expected: Object::Str("aña".into()),
+        },
+        EvalTest {
+            input: r#"rest("éa")"#,
+            expected: Object::Str("a".into()),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_hash() {
+    let tests = vec![
+        EvalTest {
+            input: "hash!{}",
+            expected: Object::Hash(Hash::new().into()),
+        },
+        EvalTest {
+            input: "hash!{1: true}",
+            expected: Object::Hash(
+                {
+                    let mut h = Hash::new();
+                    h.insert(HashableObject::Integer(1), Object::Boolean(true));
+                    h
+                }
+                .into(),
+            ),
+        },
+        EvalTest {
+            input: r#"
+            let two = "two";
+            hash!{
+                "one": 10 - 9,
+                two: 1 + 1,
+                "thr" + "ee": 6 / 2,
+                4: 4,
+                true: 5,
+                false: 6
+            }"#,
+            expected: Object::Hash(
+                {
+                    let mut h = Hash::new();
+                    h.insert(HashableObject::Str("one".into()), Object::Integer(1));
+                    h.insert(HashableObject::Str("two".into()), Object::Integer(2));
+                    h.insert(HashableObject::Str("three".into()), Object::Integer(3));
+                    h.insert(HashableObject::Integer(4), Object::Integer(4));
+                    h.insert(HashableObject::Boolean(true), Object::Integer(5));
+                    h.insert(HashableObject::Boolean(false), Object::Integer(6));
+                    h
+                }
+                .into(),
+            ),
+        },
+        EvalTest {
+            input: r#"let p = [1, 2]; let h = hash!{p: "a", [1, 3]: "b"}; h[[1, 2]]"#,
+            expected: Object::Str("a".into()),
+        },
+        EvalTest {
+            input: r#"hash!{[]: 1, [[1], "x"]: 2}[[[1], "x"]]"#,
+            expected: Object::Integer(2),
+        },
+        EvalTest {
+            input: "let n = if (false) { 1 }; hash!{n: 1}[n]",
+            expected: Object::Integer(1),
+        },
+        EvalTest {
+            input: "let n = if (false) { 1 }; hash!{[n]: 1}",
+            expected: Object::Hash(
+                Hash::from([(
+                    HashableObject::Array(vec![HashableObject::Null]),
+                    Object::Integer(1),
+                )])
+                .into(),
+            ),
+        },
+        // Keys are snapshots of the array used to build them
+        EvalTest {
+            input: "let p = [1]; let h = hash!{p: 1}; let p = push(p, 2); h[p]",
+            expected: Object::Null,
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_hash_builtins() {
+    let tests = vec![
+        EvalDisplayTest {
+            input: r#"keys(hash!{"b": 1, "a": 2, 3: 4})"#,
+            expected: "[b, a, 3]",
+        },
+        EvalDisplayTest {
+            input: r#"values(hash!{"b": 1, "a": 2})"#,
+            expected: "[1, 2]",
+        },
+        EvalDisplayTest {
+            input: r#"entries(hash!{"b": 1, [1]: 2})"#,
+            expected: "[[b, 1], [[1], 2]]",
+        },
+        EvalDisplayTest {
+            input: "keys(hash!{})",
+            expected: "[]",
+        },
+        EvalDisplayTest {
+            input: r#"has(hash!{"a": 1}, "a")"#,
+            expected: "true",
+        },
+        EvalDisplayTest {
+            input: r#"has(hash!{"a": 1}, 1)"#,
+            expected: "false",
+        },
+        EvalDisplayTest {
+            input: r#"merge(hash!{"a": 1, "b": 2}, hash!{"c": 3, "a": 4})"#,
+            expected: r#"{"a": 4, "b": 2, "c": 3}"#,
+        },
+        EvalDisplayTest {
+            input: r#"remove(hash!{"a": 1, "b": 2, "c": 3}, "b")"#,
+            expected: r#"{"a": 1, "c": 3}"#,
+        },
+        EvalDisplayTest {
+            input: r#"remove(hash!{"a": 1}, "x")"#,
+            expected: r#"{"a": 1}"#,
+        },
+        EvalDisplayTest {
+            input: r#"from_entries([["a", 1], [2, [3]]])"#,
+            expected: r#"{"a": 1, 2: [3]}"#,
+        },
+        EvalDisplayTest {
+            input: r#"from_entries(entries(hash!{"a": 1}))"#,
+            expected: r#"{"a": 1}"#,
+        },
+        EvalDisplayTest {
+            input: r#"let h = hash!{"a": 1}; let r = remove(h, "a"); let m = merge(h, hash!{"b": 2}); [h, r, m]"#,
+            expected: r#"[{"a": 1}, {}, {"a": 1, "b": 2}]"#,
+        },
+    ];
+
+    for test in tests {
+        test_eval_display(test)
+    }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: "keys([1])",
+            expected: Error::TypeError {
+                expected: ObjectType::Hash,
+                received: ObjectType::Array,
+            },
+        },
+        EvalErrorTest {
+            input: "merge(hash!{}, 1)",
+            expected: Error::TypeError {
+                expected: ObjectType::Hash,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: "has(hash!{}, fn() { 1 })",
+            expected: Error::HashError(ObjectType::Function),
+        },
+        EvalErrorTest {
+            input: "from_entries([1])",
+            expected: Error::TypeError {
+                expected: ObjectType::Array,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: "from_entries([[1]])",
+            expected: Error::ValueError(
+                "Expected an array of length 2, received one of length 1".into(),
+            ),
+        },
+        EvalErrorTest {
+            input: "remove(hash!{})",
+            expected: Error::ArgumentsError {
+                expected: 2,
+                received: 1,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
+}
+
+#[test]
+fn test_eval_hash_order() {
+    let tests = vec![
+        (r#"hash!{3: "c", 1: "a", 2: "b"}"#, "{3: c, 1: a, 2: b}"),
+        (
+            r#"hash!{"z": 1, true: 2, "a": 3, 0: 4}"#,
+            "{\"z\": 1, true: 2, \"a\": 3, 0: 4}",
+        ),
+        // Overwriting a key keeps its original position
+        ("hash!{1: 1, 2: 2, 1: 3}", "{1: 3, 2: 2}"),
+        (r#"hash!{[1, ["a"]]: 1}"#, r#"{[1, ["a"]]: 1}"#),
+    ];
+
+    for (input, expected) in tests {
+        let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+        let env = Environment::new_heap(None);
+
+        match stmts.eval_return(env, &mut Context::new()) {
+            Ok(x) => assert_eq!(x.to_string(), expected, "Failed input: {input}"),
+            Err(e) => panic!("Error evaluating: {e}"),
+        }
+    }
+
+    let a = Object::Hash(
+        Hash::from([
+            (HashableObject::Integer(1), Object::Null),
+            (HashableObject::Integer(2), Object::Null),
+        ])
+        .into(),
+    );
+    let b = Object::Hash(
+        Hash::from([
+            (HashableObject::Integer(2), Object::Null),
+            (HashableObject::Integer(1), Object::Null),
+        ])
+        .into(),
+    );
+
+    assert_eq!(a, b);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_eval_json() {
+    let tests = vec![
+        EvalTest {
+            input: r#"json_parse("[1, true, null, [], 2.0]")"#,
+            expected: Object::Array(
+                vec![
+                    Object::Integer(1),
+                    Object::Boolean(true),
+                    Object::Null,
+                    Object::Array(vec![].into()),
+                    Object::Integer(2),
+                ]
+                .into(),
+            ),
+        },
+        EvalTest {
+            input: r#"json_parse(json_stringify(hash!{"a": hash!{"b": ["c"]}}))["a"]["b"][0]"#,
+            expected: Object::Str("c".into()),
+        },
+        EvalTest {
+            input: "json_parse(json_stringify(hash!{1: 2}))",
+            expected: Object::Hash(
+                Hash::from([(HashableObject::Str("1".into()), Object::Integer(2))]).into(),
+            ),
+        },
+        EvalTest {
+            input: r#"json_stringify([1, "a", [], hash!{"b": false}])"#,
+            expected: Object::Str(r#"[1,"a",[],{"b":false}]"#.into()),
+        },
+        EvalTest {
+            input: "json_stringify(hash!{1: true})",
+            expected: Object::Str(r#"{"1":true}"#.into()),
+        },
+        EvalTest {
+            input: r#"json_stringify(hash!{[1, "a"]: 1})"#,
+            expected: Object::Str(r#"{"[1, \"a\"]":1}"#.into()),
+        },
+        EvalTest {
+            input: "json_stringify(hash!{true: [1]}, 2)",
+            expected: Object::Str("{\n  \"true\": [\n    1\n  ]\n}".into()),
+        },
+        EvalTest {
+            input: r#"let a = hash!{"x": [1, 2]}; json_parse(json_stringify(a))["x"][1]"#,
+            expected: Object::Integer(2),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: r#"json_parse("[1,")"#,
+            expected: Error::ValueError(
+                "Invalid JSON: EOF while parsing a value at line 1 column 3".into(),
+            ),
+        },
+        EvalErrorTest {
+            input: r#"json_parse("1.5")"#,
+            expected: Error::ValueError(
+                "Invalid JSON: number 1.5 is not an integer at line 1 column 3".into(),
+            ),
+        },
+        EvalErrorTest {
+            input: "json_stringify(fn(x) { x })",
+            expected: Error::ValueError("Object of type FUNCTION can't be serialized".into()),
+        },
+        EvalErrorTest {
+            input: "json_stringify(1, true)",
+            expected: Error::TypeError {
+                expected: ObjectType::Integer,
+                received: ObjectType::Boolean,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
+}
+
+#[test]
+#[cfg(feature = "regex")]
+fn test_eval_regex() {
+    let tests = vec![
+        EvalDisplayTest {
+            input: r#"re_match("id=42", "(\w+)=(\d+)")"#,
+            expected: r#"{"match": id=42, "start": 0, "end": 5, "groups": [id, 42], "named": {}}"#,
+        },
+        EvalDisplayTest {
+            input: r#"re_match("ñ 2024-10", "(?P<year>\d+)-(?P<month>\d+)(-\d+)?")"#,
+            expected: r#"{"match": 2024-10, "start": 2, "end": 9, "groups": [2024, 10, null], "named": {"year": 2024, "month": 10}}"#,
+        },
+        EvalDisplayTest {
+            input: r#"re_match("abc", "\d")"#,
+            expected: "null",
+        },
+        EvalDisplayTest {
+            input: r#"map(re_find_all("a1 ñb22 c333", "[a-zñ](\d+)"), fn(m) { [m["groups"][0], m["start"]] })"#,
+            expected: "[[1, 0], [22, 4], [333, 8]]",
+        },
+        EvalDisplayTest {
+            input: r#"re_find_all("abc", "\d")"#,
+            expected: "[]",
+        },
+        EvalDisplayTest {
+            input: r#"re_replace("2024-10-18", "(\d+)-(\d+)-(\d+)", "$3/$2/$1")"#,
+            expected: "18/10/2024",
+        },
+        EvalDisplayTest {
+            input: r#"re_replace("a1b22", "(?P<n>\d+)", "<${n}>")"#,
+            expected: "a<1>b<22>",
+        },
+        EvalDisplayTest {
+            input: r#"re_split("a, b,c ,  d", "\s*,\s*")"#,
+            expected: "[a, b, c, d]",
+        },
+    ];
+
+    for test in tests {
+        test_eval_display(test)
+    }
+
+    let tests = vec![
+        EvalErrorTest {
+            input: r#"re_match("a", 1)"#,
+            expected: Error::TypeError {
+                expected: ObjectType::Str,
+                received: ObjectType::Integer,
+            },
+        },
+        EvalErrorTest {
+            input: r#"re_replace("a", "a")"#,
+            expected: Error::ArgumentsError {
+                expected: 3,
+                received: 2,
+            },
+        },
+    ];
+
+    for test in tests {
+        test_eval_error(test)
+    }
+
+    let input = r#"try { re_match("a", "(a") } catch (e) { [e["kind"], e["message"]] }"#;
+    let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+    resolver::resolve(&mut stmts, &env).unwrap();
+
+    match stmts.eval_return(env, &mut Context::new()) {
+        Ok(Object::Array(a)) => {
+            assert_eq!(a[0], Object::from("RegexError"));
+            assert!(a[1]
+                .to_string()
+                .starts_with("Invalid regular expression (a: "));
+        }
+        x => panic!("Caught regex error expected, received {x:?}"),
+    }
+
+    // Stops at the match going over the limit rather than the whole result
+    let input = r#"re_replace("a,a,a,a", "a", "$0$0$0")"#;
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let mut ctx = Context::new().with_max_allocation(8);
+
+    assert_eq!(
+        stmts.eval_return(Environment::new_heap(None), &mut ctx),
+        Err(Error::AllocationError {
+            requested: 11,
+            limit: 8,
+        })
+    );
+}
+
+#[test]
+fn test_eval_try() {
+    let tests = vec![
+        EvalTest {
+            input: "try { 1 } catch (e) { 2 }",
+            expected: Object::Integer(1),
+        },
+        EvalTest {
+            input: "try { throw 1; 2 } catch (e) { e + 1 }",
+            expected: Object::Integer(2),
+        },
+        EvalTest {
+            input: "try { [1][5] } catch (e) { e }",
+            expected: error_object("IndexError", "Index '5' out of range"),
+        },
+        EvalTest {
+            input: r#"try { [1][5] } catch (e) { e["kind"] }"#,
+            expected: Object::Str("IndexError".into()),
+        },
+        EvalTest {
+            input: r#"try { 1 + true } catch (e) { e["message"] }"#,
+            expected: Object::Str("Unknown operation: INTEGER + BOOLEAN".into()),
+        },
+        EvalTest {
+            input: r#"
+            let safe_div = fn(a, b) {
+                if (b == 0) { throw error("ZeroDivisionError", "division by zero"); }
+                a / b
+            };
+            try { safe_div(1, 0) } catch (err) { err["kind"] }"#,
+            expected: Object::Str("ZeroDivisionError".into()),
+        },
+        EvalTest {
+            input: "try { try { throw 1 } catch (e) { throw e + 1 } } catch (e) { e * 10 }",
+            expected: Object::Integer(20),
+        },
+        EvalTest {
+            input: "let f = fn() { try { return 1; } catch (e) { 2 }; 3 }; f()",
+            expected: Object::Integer(1),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+}
+
+#[test]
+fn test_eval_traceback() {
+    let input =
+        "let inner = fn(x) {\n  x + true\n};\nlet outer = fn(y) { inner(y * 2) };\nouter(1)";
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+
+    let env = Environment::new_heap(None);
+
+    let error = stmts
+        .eval_return(env, &mut Context::new())
+        .expect_err("Input was expected to error");
+
+    assert_eq!(
+        *error.root(),
+        Error::InfixError {
+            operator: InfixOp::Plus,
+            type_left: ObjectType::Integer,
+            type_right: ObjectType::Boolean,
+        }
+    );
+
+    let frames: Vec<_> = error
+        .traceback()
+        .expect("Traceback expected")
+        .0
+        .iter()
+        .map(|f| (f.function.as_deref(), f.call.to_string(), f.location))
+        .collect();
+
+    assert_eq!(
+        frames,
+        vec![
+            (
+                Some("outer"),
+                "outer(1)".into(),
+                Location { line: 5, column: 1 },
+            ),
+            (
+                Some("inner"),
+                "inner((y * 2))".into(),
+                Location {
+                    line: 4,
+                    column: 21
+                },
+            ),
+        ]
+    );
+
+    assert_eq!(
+        error.to_string(),
+        "Traceback (most recent call last):
+  at outer (line 5, column 1): outer(1)
+  at inner (line 4, column 21): inner((y * 2))
+Unknown operation: INTEGER + BOOLEAN"
+    );
+}
+
+#[test]
+fn test_eval_caught_traceback() {
+    let input = r#"
+    let boom = fn() { throw "boom" };
+    let fail = fn() { [][0] };
+    [try { boom() } catch (e) { e }, try { fail() } catch (e) { e["traceback"] },
+     try { fail() } catch (e) { [e["location"]["line"], e["location"]["column"]] }]"#;
+
+    test_eval_output(EvalTest {
+        input,
+        expected: Object::Array(
+            vec![
+                Object::Str("boom".into()),
+                Object::Array(
+                    vec![Object::Str("at fail (line 4, column 44): fail()".into())].into(),
+                ),
+                Object::Array(vec![Object::Integer(5), Object::Integer(12)].into()),
+            ]
+            .into(),
+        ),
+    });
+}
+
+#[test]
+fn test_eval_max_depth() {
+    let input = "let f = fn(n) { 1 + f(n + 1) }; f(0)";
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+
+    let env = Environment::new_heap(None);
+    let mut ctx = Context::new().with_max_depth(50);
+
+    let error = stmts
+        .eval_return(env, &mut ctx)
+        .expect_err("Input was expected to error");
+
+    assert_eq!(*error.root(), Error::StackOverflow(50));
+    assert_eq!(error.traceback().map(|t| t.0.len()), Some(50));
+    assert_eq!(ctx.depth(), 0);
+
+    let input = r#"
+    let f = fn(n) { if (n == 0) { 0 } else { 1 + f(n - 1) } };
+    [f(40), try { f(60) } catch (e) { e["kind"] }]"#;
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+
+    let env = Environment::new_heap(None);
+    let mut ctx = Context::new().with_max_depth(50);
+
+    assert_eq!(
+        stmts.eval_return(env, &mut ctx),
+        Ok(Object::Array(
+            vec![Object::Integer(40), Object::Str("StackOverflow".into()),].into()
+        ))
+    );
+}
+
+#[test]
+fn test_eval_tail_calls() {
+    let tests = vec![
+        EvalTest {
+            input: r#"
+            let loop = fn(n) { if (n > 0) { loop(n - 1) } else { "done" } };
+            loop(100000)"#,
+            expected: Object::Str("done".into()),
+        },
+        EvalTest {
+            input: r#"
+            let sum = fn(n, acc) {
+                if (n == 0) { return acc; }
+                let next = n - 1;
+                return sum(next, acc + n);
+            };
+            sum(10000, 0)"#,
+            expected: Object::Integer(50005000),
+        },
+        EvalTest {
+            input: r#"
+            let even = fn(n) { if (n == 0) { true } else { { odd(n - 1) } } };
+            let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } };
+            [even(10001), odd(10001)]"#,
+            expected: Object::Array(vec![Object::Boolean(false), Object::Boolean(true)].into()),
+        },
+        // Calls inside try blocks aren't in tail position, so they are caught
+        EvalTest {
+            input: r#"
+            let fail = fn() { throw "oops" };
+            let f = fn() { try { fail() } catch (e) { e } };
+            f()"#,
+            expected: Object::Str("oops".into()),
+        },
+    ];
+
+    for test in tests {
+        test_eval_output(test)
+    }
+
+    let input = r#"let c = fn() { 1 + true };
+let b = fn() { c() };
+let a = fn() { b() };
+a()"#;
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let mut ctx = Context::new();
+
+    let error = stmts
+        .eval_return(Environment::new_heap(None), &mut ctx)
+        .expect_err("Input was expected to error");
+
+    // Only the entry frame and the latest tail call are kept
+    let frames: Vec<_> = error
+        .traceback()
+        .unwrap()
+        .0
+        .iter()
+        .map(|f| f.call.to_string())
+        .collect();
+
+    assert_eq!(frames, vec!["a()", "c()"]);
+    assert_eq!(ctx.depth(), 0);
+}
+
+struct LimitTest {
+    pub input: &'static str,
+    pub ctx: Context,
+    pub expected: Error,
+}
+
+#[test]
+fn test_eval_limits() {
+    let cancelled = Context::new();
+    cancelled.cancellation_handle().cancel();
+
+    let tests = vec![
+        LimitTest {
+            input: "let loop = fn() { loop() }; try { loop() } catch (e) { 1 }",
+            ctx: Context::new().with_fuel(100),
+            expected: Error::OutOfFuel,
+        },
+        LimitTest {
+            input: "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(40)",
+            ctx: Context::new().with_deadline(Instant::now()),
+            expected: Error::Timeout,
+        },
+        LimitTest {
+            input: "1 + 1",
+            ctx: cancelled,
+            expected: Error::Cancelled,
+        },
+        LimitTest {
+            input: "try { [1, 2, 3, 4] } catch (e) { 1 }",
+            ctx: Context::new().with_max_allocation(3),
+            expected: Error::AllocationError {
+                requested: 4,
+                limit: 3,
+            },
+        },
+        LimitTest {
+            input: "let a = [1, 2, 3]; push(a, 4)",
+            ctx: Context::new().with_max_allocation(3),
+            expected: Error::AllocationError {
+                requested: 4,
+                limit: 3,
+            },
+        },
+        LimitTest {
+            input: r#"let s = "joe"; s + s"#,
+            ctx: Context::new().with_max_allocation(5),
+            expected: Error::AllocationError {
+                requested: 6,
+                limit: 5,
+            },
+        },
+        LimitTest {
+            input: "hash!{1: 1, 2: 2}",
+            ctx: Context::new().with_max_allocation(1),
+            expected: Error::AllocationError {
+                requested: 2,
+                limit: 1,
+            },
+        },
+        LimitTest {
+            input: "rest(0..10000000000)",
+            ctx: Context::new().with_max_allocation(1000),
+            expected: Error::AllocationError {
+                requested: 10000000000,
+                limit: 1000,
+            },
+        },
+        LimitTest {
+            input: r#"replace("aaaa", "a", "bbb")"#,
+            ctx: Context::new().with_max_allocation(10),
+            expected: Error::AllocationError {
+                requested: 12,
+                limit: 10,
+            },
+        },
+        LimitTest {
+            input: r#"replace("ab", "", "xx")"#,
+            ctx: Context::new().with_max_allocation(5),
+            expected: Error::AllocationError {
+                requested: 8,
+                limit: 5,
+            },
+        },
+        LimitTest {
+            input: r#"join(["abc", "def"], "--")"#,
+            ctx: Context::new().with_max_allocation(7),
+            expected: Error::AllocationError {
+                requested: 8,
+                limit: 7,
+            },
+        },
+        LimitTest {
+            input: r#"format("{}{}", "abc", "def")"#,
+            ctx: Context::new().with_max_allocation(7),
+            expected: Error::AllocationError {
+                requested: 10,
+                limit: 7,
+            },
+        },
+    ];
+
+    for mut test in tests {
+        let stmts: Vec<_> = test.input.parser().collect::<Result<_, _>>().unwrap();
+
+        let env = Environment::new_heap(None);
+
+        match stmts.eval_return(env, &mut test.ctx) {
+            Ok(_) => panic!("Input '{}' was expected to error", test.input),
+            Err(e) => assert_eq!(*e.root(), test.expected, "Failed input: {}", test.input),
+        };
+    }
+
+    let input = "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(10)";
+    let stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+
+    let mut ctx = Context::new()
+        .with_fuel(1_000)
+        .with_timeout(Duration::MAX)
+        .with_max_allocation(10);
+
+    let env = Environment::new_heap(None);
+
+    assert_eq!(stmts.eval_return(env, &mut ctx), Ok(Object::Integer(0)));
+    assert!(ctx.fuel().unwrap() < 1_000);
+}
+
+#[test]
+fn test_eval_errors() {
+    let tests = vec![
+        EvalErrorTest {
             input: "5 + true;",
             expected: Error::InfixError {
                 operator: InfixOp::Plus,
@@ -578,6 +2120,16 @@ fn test_eval_errors() {
             input: "let x = true; del(\"x\"); x",
             expected: Error::IdentifierError("x".into()),
         },
+        EvalErrorTest {
+            input: "let x = true; let f = fn() { del(\"x\") }; f(); x",
+            expected: Error::IdentifierError("x".into()),
+        },
+        EvalErrorTest {
+            input: "del(\"x\")",
+            expected: Error::ValueError(
+                "Only variables declared at the top level can be deleted, x isn't one".into(),
+            ),
+        },
         EvalErrorTest {
             input: "let x = [1]; x[1]",
             expected: Error::IndexError(1),
@@ -587,30 +2139,252 @@ fn test_eval_errors() {
             expected: Error::IndexError(-1),
         },
         EvalErrorTest {
-            input: "let x = if (false) {}; hash!{x: 1}",
-            expected: Error::HashError(ObjectType::Null),
+            input: "[1, 2][1..3]",
+            expected: Error::IndexError(3),
+        },
+        EvalErrorTest {
+            input: "[1, 2][2..1]",
+            expected: Error::IndexError(1),
+        },
+        EvalErrorTest {
+            input: "[1, 2][-3..]",
+            expected: Error::IndexError(-1),
+        },
+        EvalErrorTest {
+            input: "[1, 2][true..]",
+            expected: Error::TypeError {
+                expected: ObjectType::Integer,
+                received: ObjectType::Boolean,
+            },
+        },
+        EvalErrorTest {
+            input: r#""é"[1]"#,
+            expected: Error::IndexError(1),
+        },
+        EvalErrorTest {
+            input: r#""é"[..2]"#,
+            expected: Error::IndexError(2),
+        },
+        EvalErrorTest {
+            input: r#""joe"["j"]"#,
+            expected: Error::TypeError {
+                expected: ObjectType::Integer,
+                received: ObjectType::Str,
+            },
+        },
+        EvalErrorTest {
+            input: "len(1..)",
+            expected: Error::RangeError(Range {
+                start: Some(1),
+                end: None,
+                inclusive: false,
+            }),
+        },
+        EvalErrorTest {
+            input: "(1..3)[2]",
+            expected: Error::IndexError(2),
+        },
+        EvalErrorTest {
+            input: "hash!{hash!{}: 1}",
+            expected: Error::HashError(ObjectType::Hash),
+        },
+        EvalErrorTest {
+            input: "hash!{[1, [len]]: 1}",
+            expected: Error::HashError(ObjectType::BuiltinFunction),
+        },
+        EvalErrorTest {
+            input: "throw 5",
+            expected: Error::Thrown(Object::Integer(5)),
+        },
+        EvalErrorTest {
+            input: "try { 1 } catch (e) { 2 }; throw 3",
+            expected: Error::Thrown(Object::Integer(3)),
+        },
+        EvalErrorTest {
+            input: "try { throw 1 } catch (e) { e + true }",
+            expected: Error::InfixError {
+                operator: InfixOp::Plus,
+                type_left: ObjectType::Integer,
+                type_right: ObjectType::Boolean,
+            },
+        },
+        EvalErrorTest {
+            input: "try { 1 } catch (e) { 2 }; e",
+            expected: Error::IdentifierError("e".into()),
         },
         EvalErrorTest {
             input: "let x = fn() {1}; hash!{1: 1}[x]",
             expected: Error::HashError(ObjectType::Function),
         },
+        EvalErrorTest {
+            input: "(0..=9223372036854775807)[5]",
+            expected: Error::ValueError("Range 0..=9223372036854775807 is too large".into()),
+        },
+        EvalErrorTest {
+            input: "len(-9223372036854775807..9223372036854775807)",
+            expected: Error::ValueError(
+                "Range -9223372036854775807..9223372036854775807 is too large".into(),
+            ),
+        },
+        EvalErrorTest {
+            input: "[1, 2][0..=9223372036854775807]",
+            expected: Error::IndexError(i64::MAX),
+        },
     ];
 
     for test in tests {
-        let parser = test.input.parser();
+        test_eval_error(test)
+    }
+}
 
-        let parse_result: Result<Vec<_>, ParserError> = parser.collect();
+#[test]
+fn test_eval_quote() {
+    let tests = vec![
+        EvalDisplayTest {
+            input: "quote(5)",
+            expected: "QUOTE(5)",
+        },
+        EvalDisplayTest {
+            input: "quote(5 + 8)",
+            expected: "QUOTE((5 + 8))",
+        },
+        EvalDisplayTest {
+            input: "quote(foobar + barfoo)",
+            expected: "QUOTE((foobar + barfoo))",
+        },
+        EvalDisplayTest {
+            input: "quote(unquote(4 + 4))",
+            expected: "QUOTE(8)",
+        },
+        EvalDisplayTest {
+            input: "quote(8 + unquote(4 + 4))",
+            expected: "QUOTE((8 + 8))",
+        },
+        EvalDisplayTest {
+            input: "let foobar = 8; quote(foobar)",
+            expected: "QUOTE(foobar)",
+        },
+        EvalDisplayTest {
+            input: "let foobar = 8; quote(unquote(foobar))",
+            expected: "QUOTE(8)",
+        },
+        EvalDisplayTest {
+            input: r#"quote(unquote(true) == unquote("joe"))"#,
+            expected: r#"QUOTE((true == "joe"))"#,
+        },
+        EvalDisplayTest {
+            input: "let q = quote(4 + 4); quote(unquote(4 + 4) + unquote(q))",
+            expected: "QUOTE((8 + (4 + 4)))",
+        },
+        EvalDisplayTest {
+            input: "let f = fn(x) { quote(unquote(x) * y) }; f(2)",
+            expected: "QUOTE((2 * y))",
+        },
+    ];
 
-        let env = Environment::new_heap(None);
+    for test in tests {
+        test_eval_display(test)
+    }
 
-        match parse_result {
-            Ok(stmts) => {
-                match stmts.eval_return(env) {
-                    Ok(_) => panic!("Input '{}' was expected to error", test.input),
-                    Err(e) => assert_eq!(e, test.expected),
-                };
-            }
-            Err(e) => panic!("Error parsing: {e}"),
+    let errors = vec![
+        EvalErrorTest {
+            input: "quote(1, 2)",
+            expected: Error::ArgumentsError {
+                expected: 1,
+                received: 2,
+            },
+        },
+        EvalErrorTest {
+            input: "quote(unquote([1]))",
+            expected: Error::TypeError {
+                expected: ObjectType::Quote,
+                received: ObjectType::Array,
+            },
+        },
+    ];
+
+    for test in errors {
+        test_eval_error(test)
+    }
+}
+
+#[test]
+fn test_define_macros() {
+    let input =
+        "let number = 1; let function = fn(x, y) { x + y }; let mymacro = macro(x, y) { x + y; };";
+    let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+
+    macros::define_macros(&mut stmts, &env);
+
+    assert_eq!(stmts.len(), 2);
+    assert_eq!(env.borrow().get("number"), None);
+    assert_eq!(env.borrow().get("function"), None);
+
+    let mymacro = env.borrow().get("mymacro");
+
+    match mymacro {
+        Some(Object::Macro(m)) => {
+            assert_eq!(m.node.to_string(), "macro(x, y) {\n  (x + y);\n}");
         }
+        x => panic!("Macro expected, received {x:?}"),
+    }
+}
+
+#[test]
+fn test_expand_macros() {
+    let tests = vec![
+        (
+            "let infix = macro() { quote(1 + 2); }; infix();",
+            "(1 + 2)",
+        ),
+        (
+            "let reverse = macro(a, b) { quote(unquote(b) - unquote(a)); }; reverse(2 + 2, 10 - 5);",
+            "(10 - 5) - (2 + 2)",
+        ),
+        (
+            "
+            let unless = macro(condition, consequence, alternative) {
+                quote(if (!(unquote(condition))) {
+                    unquote(consequence);
+                } else {
+                    unquote(alternative);
+                });
+            };
+            unless(10 > 5, puts(\"not greater\"), puts(\"greater\"));
+            ",
+            r#"if (!(10 > 5)) { puts("not greater") } else { puts("greater") }"#,
+        ),
+    ];
+
+    for (input, expected) in tests {
+        let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+        let expected: Vec<_> = expected.parser().collect::<Result<_, _>>().unwrap();
+        let env = Environment::new_heap(None);
+
+        macros::define_macros(&mut stmts, &env);
+        assert_eq!(
+            macros::expand_macros(&mut stmts, &env, &mut Context::new()),
+            Ok(())
+        );
+
+        // Call locations point to where the code was written
+        let display =
+            |stmts: Vec<Statement>| stmts.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(display(stmts), display(expected), "Failed input: {input}");
     }
+
+    let input = "let m = macro(x) { x + 1 }; m(1)";
+    let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+
+    macros::define_macros(&mut stmts, &env);
+    assert_eq!(
+        macros::expand_macros(&mut stmts, &env, &mut Context::new()),
+        Err(Error::InfixError {
+            operator: InfixOp::Plus,
+            type_left: ObjectType::Quote,
+            type_right: ObjectType::Integer,
+        })
+    );
 }
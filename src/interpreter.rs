@@ -0,0 +1,272 @@
+#[cfg(test)]
+mod test;
+
+use crate::evaluator::context::Context;
+use crate::evaluator::error::Error as EvalError;
+use crate::evaluator::error::Result as EvalResult;
+use crate::evaluator::modules::ModuleLoader;
+use crate::evaluator::object::builtins::{Builtin, Capability};
+use crate::evaluator::object::environment::{Environment, HeapEnvironment};
+use crate::evaluator::object::native::{Arity, Native};
+use crate::evaluator::object::Object;
+use crate::evaluator::{macros, prelude, Evaluate};
+use crate::parser::error::Error as ParserError;
+use crate::parser::Parse;
+use crate::resolver::{self, Diagnostics};
+
+use std::collections::HashSet;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum Error {
+    #[error("Parsing error: {0}")]
+    Parse(#[from] ParserError),
+    #[error("{0}")]
+    Resolve(#[from] Diagnostics),
+    #[error("{0}")]
+    Eval(#[from] EvalError),
+}
+
+pub type Result<T, E = Error> = core::result::Result<T, E>;
+
+/// Configures what scripts run by an `Interpreter` are allowed to do. Only
+/// `Capability::DEFAULT` is granted unless allowed, files in particular
+/// can't be read, written or imported.
+pub struct InterpreterBuilder {
+    capabilities: HashSet<Capability>,
+    max_depth: usize,
+    fuel: Option<u64>,
+    timeout: Option<Duration>,
+    max_allocation: Option<usize>,
+    stdout: Option<Box<dyn Write>>,
+    stderr: Option<Box<dyn Write>>,
+    stdin: Option<Box<dyn Read>>,
+    loader: Option<Box<dyn ModuleLoader>>,
+    natives: Vec<Native>,
+    prelude: bool,
+}
+
+impl Default for InterpreterBuilder {
+    fn default() -> Self {
+        Self {
+            capabilities: HashSet::from(Capability::DEFAULT),
+            max_depth: Context::DEFAULT_MAX_DEPTH,
+            fuel: None,
+            timeout: None,
+            max_allocation: None,
+            stdout: None,
+            stderr: None,
+            stdin: None,
+            loader: None,
+            natives: Vec::new(),
+            prelude: true,
+        }
+    }
+}
+
+impl InterpreterBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Denies every capability, only pure builtins stay callable.
+    pub fn sandboxed(mut self) -> Self {
+        self.capabilities.clear();
+        self
+    }
+
+    /// Grants every capability, including reading and writing files and
+    /// importing modules from them, for scripts the host trusts.
+    pub fn trusted(mut self) -> Self {
+        self.capabilities = HashSet::from(Capability::ALL);
+        self
+    }
+
+    pub fn allow(mut self, capability: Capability) -> Self {
+        self.capabilities.insert(capability);
+        self
+    }
+
+    pub fn deny(mut self, capability: Capability) -> Self {
+        self.capabilities.remove(&capability);
+        self
+    }
+
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Fuel available to each call to `Interpreter::eval`.
+    pub fn fuel(mut self, fuel: u64) -> Self {
+        self.fuel = Some(fuel);
+        self
+    }
+
+    /// Time available to each call to `Interpreter::eval`.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn max_allocation(mut self, max_allocation: usize) -> Self {
+        self.max_allocation = Some(max_allocation);
+        self
+    }
+
+    pub fn stdout(mut self, stdout: impl Write + 'static) -> Self {
+        self.stdout = Some(Box::new(stdout));
+        self
+    }
+
+    pub fn stderr(mut self, stderr: impl Write + 'static) -> Self {
+        self.stderr = Some(Box::new(stderr));
+        self
+    }
+
+    pub fn stdin(mut self, stdin: impl Read + 'static) -> Self {
+        self.stdin = Some(Box::new(stdin));
+        self
+    }
+
+    /// Replaces where `import` reads modules from, files relative to the
+    /// working directory by default.
+    pub fn loader(mut self, loader: impl ModuleLoader + 'static) -> Self {
+        self.loader = Some(Box::new(loader));
+        self
+    }
+
+    /// Exposes a Rust function to scripts and modules as a global named `name`.
+    pub fn native(
+        mut self,
+        name: &str,
+        arity: Arity,
+        func: impl Fn(&[Object], &mut Context) -> EvalResult<Object> + 'static,
+    ) -> Self {
+        self.natives.push(Native::new(name, arity, func));
+        self
+    }
+
+    /// Whether the standard library written in Monkey is defined, as it is
+    /// by default.
+    pub fn prelude(mut self, prelude: bool) -> Self {
+        self.prelude = prelude;
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
+        let mut ctx = Context::new()
+            .with_max_depth(self.max_depth)
+            .with_capabilities(self.capabilities);
+
+        if let Some(fuel) = self.fuel {
+            ctx = ctx.with_fuel(fuel);
+        }
+        if let Some(timeout) = self.timeout {
+            ctx = ctx.with_timeout(timeout);
+        }
+        if let Some(max_allocation) = self.max_allocation {
+            ctx = ctx.with_max_allocation(max_allocation);
+        }
+        if let Some(stdout) = self.stdout {
+            ctx = ctx.with_stdout(stdout);
+        }
+        if let Some(stderr) = self.stderr {
+            ctx = ctx.with_stderr(stderr);
+        }
+        if let Some(stdin) = self.stdin {
+            ctx = ctx.with_stdin(stdin);
+        }
+        if let Some(loader) = self.loader {
+            ctx = ctx.with_loader(loader);
+        }
+
+        let builtins = Builtin::environment();
+
+        if self.prelude {
+            prelude::load(&builtins);
+        }
+
+        // Shared with modules, which don't see the script's own globals
+        let globals = Environment::new_heap(Some(builtins));
+
+        for native in self.natives {
+            let name = native.name.clone();
+
+            globals
+                .borrow_mut()
+                .store
+                .insert(name, Object::Native(native));
+        }
+
+        Interpreter {
+            env: Environment::new_heap(Some(globals.clone())),
+            ctx: ctx.with_module_globals(globals.clone()),
+            globals,
+        }
+    }
+}
+
+/// Evaluates source code in a global environment kept between calls.
+pub struct Interpreter {
+    env: HeapEnvironment,
+    ctx: Context,
+    /// Natives, visible to the script and its modules
+    globals: HeapEnvironment,
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        InterpreterBuilder::default().build()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn builder() -> InterpreterBuilder {
+        InterpreterBuilder::default()
+    }
+
+    pub fn env(&self) -> HeapEnvironment {
+        self.env.clone()
+    }
+
+    pub fn context(&self) -> &Context {
+        &self.ctx
+    }
+
+    pub fn context_mut(&mut self) -> &mut Context {
+        &mut self.ctx
+    }
+
+    /// Exposes a Rust function to scripts and modules as a global named `name`.
+    pub fn register(
+        &mut self,
+        name: &str,
+        arity: Arity,
+        func: impl Fn(&[Object], &mut Context) -> EvalResult<Object> + 'static,
+    ) {
+        let native = Native::new(name, arity, func);
+
+        self.globals.borrow_mut().set(name, Object::Native(native));
+    }
+
+    pub fn eval(&mut self, input: &str) -> Result<Object> {
+        let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>()?;
+
+        self.ctx.reset();
+
+        macros::define_macros(&mut stmts, &self.env);
+        macros::expand_macros(&mut stmts, &self.env, &mut self.ctx)?;
+
+        resolver::resolve(&mut stmts, &self.env)?;
+
+        Ok(stmts.eval_return(self.env.clone(), &mut self.ctx)?)
+    }
+}
@@ -0,0 +1,550 @@
+use super::{Error, Interpreter};
+use crate::evaluator::context::OutputBuffer;
+use crate::evaluator::error::Error as EvalError;
+use crate::evaluator::modules::FileSystemLoader;
+use crate::evaluator::object::builtins::Capability;
+use crate::evaluator::object::native::Arity;
+use crate::evaluator::object::{Object, ObjectType};
+use crate::lexer::token::Location;
+use crate::resolver::{Diagnostics, Error as ResolveError};
+
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::time::Duration;
+use std::{env, fs, process};
+
+struct PermissionTest {
+    pub input: &'static str,
+    pub builtin: &'static str,
+    pub capability: Capability,
+}
+
+#[test]
+fn test_interpreter_keeps_globals() {
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(interpreter.eval("let a = 5;"), Ok(Object::Null));
+    assert_eq!(interpreter.eval("a * 2"), Ok(Object::Integer(10)));
+}
+
+#[test]
+fn test_interpreter_sandboxed() {
+    let tests = vec![
+        PermissionTest {
+            input: r#"puts("joe")"#,
+            builtin: "puts",
+            capability: Capability::Io,
+        },
+        PermissionTest {
+            input: r#"let a = 1; del("a")"#,
+            builtin: "del",
+            capability: Capability::EnvMutation,
+        },
+        PermissionTest {
+            input: "time()",
+            builtin: "time",
+            capability: Capability::Time,
+        },
+        PermissionTest {
+            input: "random(10)",
+            builtin: "random",
+            capability: Capability::Random,
+        },
+        PermissionTest {
+            input: r#"read_file("/etc/passwd")"#,
+            builtin: "read_file",
+            capability: Capability::Fs,
+        },
+        PermissionTest {
+            input: r#"write_file("joe.txt", "mama")"#,
+            builtin: "write_file",
+            capability: Capability::Fs,
+        },
+    ];
+
+    for test in tests {
+        let mut interpreter = Interpreter::builder().sandboxed().build();
+
+        assert_eq!(
+            interpreter.eval(test.input),
+            Err(Error::Eval(EvalError::PermissionError {
+                builtin: test.builtin.into(),
+                capability: test.capability,
+            })),
+            "Failed input: {}",
+            test.input
+        );
+    }
+
+    let mut interpreter = Interpreter::builder().sandboxed().build();
+
+    assert_eq!(
+        interpreter.eval(r#"len("joe") + len([1, 2])"#),
+        Ok(Object::Integer(5))
+    );
+    assert_eq!(
+        interpreter.eval(r#"try { puts(1) } catch (e) { e["kind"] }"#),
+        Ok(Object::Str("PermissionError".into()))
+    );
+}
+
+#[test]
+fn test_interpreter_default_capabilities() {
+    let denied = vec![
+        (r#"write_file("x", "y")"#, "write_file", Capability::Fs),
+        (r#"read_file("x")"#, "read_file", Capability::Fs),
+        ("time()", "time", Capability::Time),
+        ("random(2)", "random", Capability::Random),
+    ];
+
+    for (input, builtin, capability) in denied {
+        let mut interpreter = Interpreter::new();
+
+        assert_eq!(
+            interpreter.eval(input),
+            Err(Error::Eval(EvalError::PermissionError {
+                builtin: builtin.into(),
+                capability,
+            })),
+            "Failed input: {input}"
+        );
+    }
+
+    let mut interpreter = Interpreter::builder().stdout(OutputBuffer::new()).build();
+    assert_eq!(
+        interpreter.eval(r#"let a = 1; puts(a); del("a")"#),
+        Ok(Object::Integer(1))
+    );
+
+    let mut interpreter = Interpreter::builder().trusted().build();
+    assert_eq!(interpreter.eval("random(1)"), Ok(Object::Integer(0)));
+}
+
+#[test]
+fn test_interpreter_capabilities() {
+    let mut interpreter = Interpreter::builder()
+        .sandboxed()
+        .allow(Capability::Random)
+        .allow(Capability::Time)
+        .deny(Capability::Time)
+        .build();
+
+    match interpreter.eval("random(10)") {
+        Ok(Object::Integer(i)) => assert!((0..10).contains(&i)),
+        x => panic!("Integer expected, received {x:?}"),
+    }
+
+    assert!(interpreter.eval("time()").is_err());
+}
+
+#[test]
+fn test_interpreter_limits_per_eval() {
+    let mut interpreter = Interpreter::builder().fuel(100).build();
+
+    let input = "let f = fn(n) { if (n == 0) { 0 } else { f(n - 1) } }; f(3)";
+
+    // Each evaluation receives a fresh budget
+    assert_eq!(interpreter.eval(input), Ok(Object::Integer(0)));
+    assert_eq!(interpreter.eval(input), Ok(Object::Integer(0)));
+    match interpreter.eval("f(100)") {
+        Err(Error::Eval(e)) => assert_eq!(e.root(), &EvalError::OutOfFuel),
+        x => panic!("OutOfFuel expected, received {x:?}"),
+    }
+}
+
+#[test]
+fn test_interpreter_unbounded_timeout() {
+    let mut interpreter = Interpreter::builder().timeout(Duration::MAX).build();
+
+    assert_eq!(interpreter.eval("1 + 1"), Ok(Object::Integer(2)));
+    assert_eq!(interpreter.eval("2 + 2"), Ok(Object::Integer(4)));
+}
+
+#[test]
+fn test_interpreter_redirected_io() {
+    let stdout = OutputBuffer::new();
+    let stderr = OutputBuffer::new();
+
+    let mut interpreter = Interpreter::builder()
+        .stdout(stdout.clone())
+        .stderr(stderr.clone())
+        .stdin("joe\r\nmama".as_bytes())
+        .build();
+
+    assert_eq!(
+        interpreter.eval(r#"puts("a", 1, [true]); puts()"#),
+        Ok(Object::Null)
+    );
+    assert_eq!(stdout.contents(), "a\n1\n[true]\n\n");
+
+    assert_eq!(interpreter.eval(r#"eputs("oops")"#), Ok(Object::Null));
+    assert_eq!(stderr.contents(), "oops\n");
+
+    stdout.clear();
+
+    assert_eq!(
+        interpreter.eval(r#"input("name: ")"#),
+        Ok(Object::Str("joe".into()))
+    );
+    assert_eq!(stdout.contents(), "name: ");
+    assert_eq!(interpreter.eval("input()"), Ok(Object::Str("mama".into())));
+    assert_eq!(interpreter.eval("input()"), Ok(Object::Null));
+}
+
+#[test]
+fn test_interpreter_natives() {
+    let calls = Rc::new(Cell::new(0));
+    let counter = calls.clone();
+
+    let mut interpreter = Interpreter::builder()
+        .native("double", Arity::Exact(1), |args, _| match &args[0] {
+            Object::Integer(i) => Ok(Object::Integer(i * 2)),
+            o => Err(EvalError::TypeError {
+                expected: ObjectType::Integer,
+                received: o.into(),
+            }),
+        })
+        .native("head", Arity::AtLeast(1), |args, _| Ok(args[0].clone()))
+        .build();
+
+    interpreter.register("count", Arity::AtLeast(0), move |args, _| {
+        counter.set(counter.get() + 1);
+
+        Ok(Object::Integer(args.len() as i64))
+    });
+
+    assert_eq!(interpreter.eval("double(21)"), Ok(Object::Integer(42)));
+    assert_eq!(
+        interpreter.eval("let twice = fn(f, x) { f(f(x)) }; twice(double, 3)"),
+        Ok(Object::Integer(12))
+    );
+    assert_eq!(interpreter.eval("count(1, 2, 3)"), Ok(Object::Integer(3)));
+    assert_eq!(
+        interpreter.eval("count() + count(1)"),
+        Ok(Object::Integer(1))
+    );
+    assert_eq!(calls.get(), 3);
+
+    assert_eq!(
+        interpreter.eval("double(1, 2)"),
+        Err(Error::Eval(EvalError::ArgumentsError {
+            expected: 1,
+            received: 2
+        }))
+    );
+    assert_eq!(interpreter.eval("head(1, 2)"), Ok(Object::Integer(1)));
+    assert_eq!(
+        interpreter.eval("head()"),
+        Err(Error::Eval(EvalError::TooFewArgumentsError {
+            minimum: 1,
+            received: 0
+        }))
+    );
+    assert_eq!(
+        interpreter.eval("head()").map_err(|e| e.to_string()),
+        Err("Expected at least 1 arguments, 0 received".into())
+    );
+    assert_eq!(
+        interpreter.eval(r#"try { double("a") } catch (e) { e["kind"] }"#),
+        Ok(Object::Str("TypeError".into()))
+    );
+    assert_eq!(
+        interpreter.eval("double").map(|o| o.to_string()),
+        Ok("fn double(_) { NATIVE }".into())
+    );
+    assert_eq!(
+        interpreter.eval("count").map(|o| o.to_string()),
+        Ok("fn count(...) { NATIVE }".into())
+    );
+}
+
+#[test]
+fn test_interpreter_resolves() {
+    let mut interpreter = Interpreter::new();
+
+    assert_eq!(
+        interpreter.eval("let add = fn(a) { fn(b) { a + b } };"),
+        Ok(Object::Null)
+    );
+    assert_eq!(interpreter.eval("add(2)(3)"), Ok(Object::Integer(5)));
+
+    match interpreter.eval("let f = fn() { x };\nx + y") {
+        Err(Error::Resolve(Diagnostics(errors))) => assert_eq!(
+            errors,
+            vec![
+                ResolveError::Undefined {
+                    name: "x".into(),
+                    location: Location {
+                        line: 1,
+                        column: 16
+                    },
+                },
+                ResolveError::Undefined {
+                    name: "x".into(),
+                    location: Location { line: 2, column: 1 },
+                },
+                ResolveError::Undefined {
+                    name: "y".into(),
+                    location: Location { line: 2, column: 5 },
+                },
+            ]
+        ),
+        x => panic!("Resolve error expected, received {x:?}"),
+    }
+
+    // Nothing runs when resolution fails
+    assert_eq!(
+        interpreter.eval("let f = 1; undefined"),
+        Err(Error::Resolve(Diagnostics(vec![ResolveError::Undefined {
+            name: "undefined".into(),
+            location: Location {
+                line: 1,
+                column: 12
+            },
+        }])))
+    );
+    assert!(interpreter.eval("f").is_err());
+}
+
+#[test]
+fn test_interpreter_macros() {
+    let mut interpreter = Interpreter::new();
+
+    let unless = "
+        let unless = macro(condition, consequence, alternative) {
+            quote(if (!(unquote(condition))) { unquote(consequence) } else { unquote(alternative) })
+        };
+    ";
+    assert_eq!(interpreter.eval(unless), Ok(Object::Null));
+
+    // Macros are kept between evaluations
+    assert_eq!(
+        interpreter.eval("let x = 3; unless(x > 5, x * 2, x)"),
+        Ok(Object::Integer(6))
+    );
+    assert_eq!(
+        interpreter.eval("let f = fn(n) { unless(n == 0, n + f(n - 1), 0) }; f(4)"),
+        Ok(Object::Integer(10))
+    );
+
+    // Only the chosen branch is evaluated
+    assert_eq!(
+        interpreter.eval(r#"unless(true, { throw "oops" }, 1)"#),
+        Ok(Object::Integer(1))
+    );
+
+    assert_eq!(
+        interpreter.eval("let m = macro() { 1 }; m()"),
+        Err(Error::Eval(EvalError::TypeError {
+            expected: ObjectType::Quote,
+            received: ObjectType::Integer,
+        }))
+    );
+}
+
+#[test]
+fn test_interpreter_prelude() {
+    let tests = vec![
+        ("first([1, 2, 3])", "1"),
+        ("first([])", "null"),
+        ("last([1, 2, 3])", "3"),
+        (r#"last("abc")"#, "c"),
+        ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+        ("filter(1..7, fn(x) { x / 2 * 2 == x })", "[2, 4, 6]"),
+        ("reduce([1, 2, 3], 10, fn(acc, x) { acc + x })", "16"),
+        ("range(2, 5)", "[2, 3, 4]"),
+        ("range(5, 2)", "[]"),
+        ("contains([1, 2, 3], 2)", "true"),
+        (r#"contains(["a"], "b")"#, "false"),
+        ("reverse([1, 2, 3])", "[3, 2, 1]"),
+        ("sum(range(0, 10000))", "49995000"),
+        ("each([1, 2], fn(x) { puts(x) })", "null"),
+        ("let map = fn(a, f) { 0 }; map([1], fn(x) { x })", "0"),
+    ];
+
+    for (input, expected) in tests {
+        let mut interpreter = Interpreter::builder().stdout(OutputBuffer::new()).build();
+
+        match interpreter.eval(input) {
+            Ok(o) => assert_eq!(o.to_string(), expected, "Failed input: {input}"),
+            Err(e) => panic!("Failed input: {input}, error: {e}"),
+        }
+    }
+
+    // Modules see the prelude too
+    let mut interpreter = Interpreter::builder()
+        .loader(modules(&[(
+            "lib",
+            "export let doubled = map([1], fn(x) { x * 2 });",
+        )]))
+        .build();
+    assert_eq!(
+        interpreter
+            .eval(r#"import "lib" as lib; lib["doubled"]"#)
+            .map(|o| o.to_string()),
+        Ok("[2]".into())
+    );
+
+    let mut interpreter = Interpreter::builder().prelude(false).build();
+    assert_eq!(
+        interpreter.eval("first([1])"),
+        Err(Error::Resolve(Diagnostics(vec![ResolveError::Undefined {
+            name: "first".into(),
+            location: Location { line: 1, column: 1 },
+        }])))
+    );
+    assert_eq!(interpreter.eval("len([1])"), Ok(Object::Integer(1)));
+}
+
+fn modules(modules: &[(&str, &str)]) -> HashMap<String, String> {
+    modules
+        .iter()
+        .map(|(path, source)| (path.to_string(), source.to_string()))
+        .collect()
+}
+
+#[test]
+fn test_interpreter_modules() {
+    let stdout = OutputBuffer::new();
+
+    let mut interpreter = Interpreter::builder()
+        .stdout(stdout.clone())
+        .native("double", Arity::Exact(1), |args, _| match &args[0] {
+            Object::Integer(i) => Ok(Object::Integer(i * 2)),
+            _ => Ok(Object::Null),
+        })
+        .loader(modules(&[
+            (
+                "math",
+                r#"
+                puts("loading math");
+                let square = fn(x) { x * x };
+                export let pow4 = fn(x) { square(square(x)) };
+                export let quad = fn(x) { double(double(x)) };
+                "#,
+            ),
+            (
+                "stats",
+                r#"
+                import "math" as m;
+                export let sum_pow4 = fn(a, b) { m["pow4"](a) + m["pow4"](b) };
+                "#,
+            ),
+        ]))
+        .build();
+
+    assert_eq!(
+        interpreter.eval(r#"import "math" as math; math["pow4"](2) + math["quad"](1)"#),
+        Ok(Object::Integer(20))
+    );
+
+    // Only exports are visible, and each module is evaluated once
+    assert_eq!(
+        interpreter.eval(r#"import "stats" as stats; stats["sum_pow4"](1, 2)"#),
+        Ok(Object::Integer(17))
+    );
+    assert_eq!(interpreter.eval(r#"math["square"]"#), Ok(Object::Null));
+    assert_eq!(stdout.contents(), "loading math\n");
+
+    assert_eq!(
+        interpreter.eval(r#"fn() { import "math" as local; local["pow4"](3) }()"#),
+        Ok(Object::Integer(81))
+    );
+}
+
+#[test]
+fn test_interpreter_module_errors() {
+    let mut interpreter = Interpreter::builder()
+        .loader(modules(&[
+            ("a", r#"import "b" as b; export let x = 1;"#),
+            ("b", r#"import "c" as c;"#),
+            ("c", r#"import "a" as a;"#),
+            ("script_globals", "export let y = secret;"),
+            ("broken", "let x = ;"),
+            ("nested", "fn() { export let x = 1; }"),
+        ]))
+        .build();
+
+    let tests = vec![
+        (
+            r#"import "a" as a;"#,
+            EvalError::ImportCycle(vec!["a".into(), "b".into(), "c".into(), "a".into()]),
+        ),
+        (
+            r#"import "missing" as m;"#,
+            EvalError::ImportError {
+                path: "missing".into(),
+                reason: "entity not found".into(),
+            },
+        ),
+        (
+            r#"let secret = 1; import "script_globals" as g;"#,
+            EvalError::ImportError {
+                path: "script_globals".into(),
+                reason: "Undefined variable secret at line 1, column 16".into(),
+            },
+        ),
+        (
+            r#"import "broken" as b;"#,
+            EvalError::ImportError {
+                path: "broken".into(),
+                reason: "Parsing error: Expression expected".into(),
+            },
+        ),
+        (
+            r#"import "nested" as n;"#,
+            EvalError::ImportError {
+                path: "nested".into(),
+                reason: "Export of x at line 1, column 19 isn't at the top level".into(),
+            },
+        ),
+    ];
+
+    for (input, expected) in tests {
+        match interpreter.eval(input) {
+            Err(Error::Eval(e)) => assert_eq!(e.root(), &expected, "Failed input: {input}"),
+            x => panic!("{expected:?} expected, received {x:?}"),
+        }
+    }
+
+    assert_eq!(
+        interpreter.eval(r#"try { import "a" as a; 1 } catch (e) { e["kind"] }"#),
+        Ok(Object::Str("ImportCycle".into()))
+    );
+
+    let mut interpreter = Interpreter::builder().sandboxed().build();
+
+    assert_eq!(
+        interpreter.eval(r#"import "lib.mk" as lib;"#),
+        Err(Error::Eval(EvalError::PermissionError {
+            builtin: "import".into(),
+            capability: Capability::Fs,
+        }))
+    );
+}
+
+#[test]
+fn test_interpreter_file_modules() {
+    let root = env::temp_dir().join(format!("interpret-deez-modules-{}", process::id()));
+    fs::create_dir_all(root.join("lib")).unwrap();
+
+    fs::write(
+        root.join("lib/strings.mk"),
+        r#"import "chars.mk" as chars; export let greet = fn(name) { chars["hi"] + name };"#,
+    )
+    .unwrap();
+    fs::write(root.join("lib/chars.mk"), r#"export let hi = "hi ";"#).unwrap();
+
+    let mut interpreter = Interpreter::builder()
+        .allow(Capability::Fs)
+        .loader(FileSystemLoader::new(&root))
+        .build();
+
+    // Imports from a module are relative to it
+    let result = interpreter.eval(r#"import "lib/strings.mk" as s; s["greet"]("joe")"#);
+
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(result, Ok(Object::Str("hi joe".into())));
+}
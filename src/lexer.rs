@@ -5,13 +5,15 @@ pub mod token;
 use std::iter::Iterator;
 use std::str::Chars;
 
-use token::Token;
+use token::{Location, Token};
 
 pub struct Lexer<'a> {
     input: &'a str,        // Used to create slices
     input_iter: Chars<'a>, // Used to iterate only once
     position: usize,       // Used to calculate slices
     char: Option<char>,
+    location: Location,       // Location of the current char
+    token_location: Location, // Location of the last token
 }
 
 pub trait Tokenize {
@@ -33,6 +35,8 @@ impl<'a> Lexer<'a> {
             input_iter: input.chars(),
             position: 0,
             char: None,
+            location: Location::default(),
+            token_location: Location::default(),
             input,
         };
 
@@ -40,16 +44,35 @@ impl<'a> Lexer<'a> {
         l
     }
 
+    /// Where the last token returned by the lexer starts.
+    pub fn token_location(&self) -> Location {
+        self.token_location
+    }
+
     fn read_char(&mut self) -> Option<char> {
         self.position += match self.char {
             Some(ch) => ch.len_utf8(),
             None => 0,
         };
 
+        match self.char {
+            Some('\n') => {
+                self.location.line += 1;
+                self.location.column = 1;
+            }
+            Some(_) => self.location.column += 1,
+            None => (),
+        }
+
         self.char = self.input_iter.next();
         self.char
     }
 
+    /// The character after the current one.
+    fn peek_char(&self) -> Option<char> {
+        self.input_iter.clone().next()
+    }
+
     fn read_ident(&mut self) -> &'a str {
         let old_pos = self.position;
 
@@ -58,6 +81,8 @@ impl<'a> Lexer<'a> {
             match ch {
                 '=' | '+' | '-' | '!' | '*' | '/' | '<' | '>' | ',' | ';' | '(' | ')' | '{'
                 | '}' | ' ' | '[' | ']' | ':' | '\t' | '\r' | '\n' => break,
+                // A single dot is part of the identifier, as before ranges
+                '.' if self.peek_char() == Some('.') => break,
                 _ => {
                     self.read_char();
                 }
@@ -102,6 +127,7 @@ impl Iterator for Lexer<'_> {
     type Item = Token;
     fn next(&mut self) -> Option<Token> {
         self.skip_whitespace();
+        self.token_location = self.location;
 
         let out = match self.char {
             Some('+') => Some(Token::Plus),
@@ -135,6 +161,15 @@ impl Iterator for Lexer<'_> {
                     return Some(Token::Bang);
                 }
             }
+            Some('.') if self.peek_char() == Some('.') => {
+                self.read_char();
+
+                if let Some('=') = self.read_char() {
+                    self.read_char();
+                    return Some(Token::DotDotEq);
+                }
+                return Some(Token::DotDot);
+            }
             Some('"') => {
                 let str = self.read_str();
                 Some(Token::Str(str.into()))
@@ -147,12 +182,19 @@ impl Iterator for Lexer<'_> {
                 let ident = self.read_ident();
                 return Some(match ident {
                     "fn" => Token::Function,
+                    "macro" => Token::Macro,
                     "let" => Token::Let,
                     "true" => Token::True,
                     "false" => Token::False,
                     "if" => Token::If,
                     "else" => Token::Else,
                     "return" => Token::Return,
+                    "try" => Token::Try,
+                    "catch" => Token::Catch,
+                    "throw" => Token::Throw,
+                    "import" => Token::Import,
+                    "as" => Token::As,
+                    "export" => Token::Export,
                     "hash" => {
                         if self.char == Some('!') {
                             self.read_char();
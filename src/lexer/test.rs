@@ -1,5 +1,6 @@
 use crate::Tokenize;
 
+use super::token::Location;
 use super::Token;
 
 #[test]
@@ -164,3 +165,169 @@ fn get_next_complete() {
 
     assert_eq!(lexer.next(), None)
 }
+
+#[test]
+fn get_range_tokens() {
+    let input = "1..3; a..=b; ..x; arr[2..]; a.b..c";
+    let mut lexer = input.tokenize();
+
+    let tokens = vec![
+        Token::Int("1".into()),
+        Token::DotDot,
+        Token::Int("3".into()),
+        Token::Semicolon,
+        Token::Ident("a".into()),
+        Token::DotDotEq,
+        Token::Ident("b".into()),
+        Token::Semicolon,
+        Token::DotDot,
+        Token::Ident("x".into()),
+        Token::Semicolon,
+        Token::Ident("arr".into()),
+        Token::LSquare,
+        Token::Int("2".into()),
+        Token::DotDot,
+        Token::RSquare,
+        Token::Semicolon,
+        Token::Ident("a.b".into()),
+        Token::DotDot,
+        Token::Ident("c".into()),
+    ];
+
+    for token in tokens {
+        if let Some(next_token) = lexer.next() {
+            assert_eq!(token, next_token);
+        } else {
+            panic!("Lexer finished sooner than expected")
+        };
+    }
+
+    assert_eq!(lexer.next(), None)
+}
+
+#[test]
+fn get_exception_tokens() {
+    let input = "try { throw e; } catch (e) { e }";
+    let mut lexer = input.tokenize();
+
+    let tokens = vec![
+        Token::Try,
+        Token::LBrace,
+        Token::Throw,
+        Token::Ident("e".into()),
+        Token::Semicolon,
+        Token::RBrace,
+        Token::Catch,
+        Token::LParen,
+        Token::Ident("e".into()),
+        Token::RParen,
+        Token::LBrace,
+        Token::Ident("e".into()),
+        Token::RBrace,
+    ];
+
+    for token in tokens {
+        if let Some(next_token) = lexer.next() {
+            assert_eq!(token, next_token);
+        } else {
+            panic!("Lexer finished sooner than expected")
+        };
+    }
+
+    assert_eq!(lexer.next(), None)
+}
+
+#[test]
+fn get_macro_tokens() {
+    let input = "let m = macro(x) { quote(unquote(x)) };";
+    let mut lexer = input.tokenize();
+
+    let tokens = vec![
+        Token::Let,
+        Token::Ident("m".into()),
+        Token::Assign,
+        Token::Macro,
+        Token::LParen,
+        Token::Ident("x".into()),
+        Token::RParen,
+        Token::LBrace,
+        Token::Ident("quote".into()),
+        Token::LParen,
+        Token::Ident("unquote".into()),
+        Token::LParen,
+        Token::Ident("x".into()),
+        Token::RParen,
+        Token::RParen,
+        Token::RBrace,
+        Token::Semicolon,
+    ];
+
+    for token in tokens {
+        if let Some(next_token) = lexer.next() {
+            assert_eq!(token, next_token);
+        } else {
+            panic!("Lexer finished sooner than expected")
+        };
+    }
+
+    assert_eq!(lexer.next(), None)
+}
+
+#[test]
+fn get_module_tokens() {
+    let input = r#"import "lib.mk" as lib; export let x = 1;"#;
+    let mut lexer = input.tokenize();
+
+    let tokens = vec![
+        Token::Import,
+        Token::Str("lib.mk".into()),
+        Token::As,
+        Token::Ident("lib".into()),
+        Token::Semicolon,
+        Token::Export,
+        Token::Let,
+        Token::Ident("x".into()),
+        Token::Assign,
+        Token::Int("1".into()),
+        Token::Semicolon,
+    ];
+
+    for token in tokens {
+        if let Some(next_token) = lexer.next() {
+            assert_eq!(token, next_token);
+        } else {
+            panic!("Lexer finished sooner than expected")
+        };
+    }
+
+    assert_eq!(lexer.next(), None)
+}
+
+#[test]
+fn get_token_locations() {
+    let input = "let x = 5;\n  f(x)";
+    let mut lexer = input.tokenize();
+
+    let locations = vec![
+        (Token::Let, Location { line: 1, column: 1 }),
+        (Token::Ident("x".into()), Location { line: 1, column: 5 }),
+        (Token::Assign, Location { line: 1, column: 7 }),
+        (Token::Int("5".into()), Location { line: 1, column: 9 }),
+        (
+            Token::Semicolon,
+            Location {
+                line: 1,
+                column: 10,
+            },
+        ),
+        (Token::Ident("f".into()), Location { line: 2, column: 3 }),
+        (Token::LParen, Location { line: 2, column: 4 }),
+        (Token::Ident("x".into()), Location { line: 2, column: 5 }),
+        (Token::RParen, Location { line: 2, column: 6 }),
+    ];
+
+    for (token, location) in locations {
+        assert_eq!(lexer.next(), Some(token));
+        assert_eq!(lexer.token_location(), location);
+    }
+}
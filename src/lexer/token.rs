@@ -25,6 +25,8 @@ pub enum Token {
     Comma,
     Semicolon,
     Colon,
+    DotDot,
+    DotDotEq,
 
     LParen,
     RParen,
@@ -34,6 +36,7 @@ pub enum Token {
     RSquare,
     // Keywords
     Function,
+    Macro,
     Let,
     True,
     False,
@@ -41,18 +44,29 @@ pub enum Token {
     Else,
     Return,
     HashMacro,
+    Try,
+    Catch,
+    Throw,
+    Import,
+    As,
+    Export,
 }
 
 impl fmt::Display for Token {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            Self::As => write!(f, "as"),
             Self::Assign => write!(f, "="),
             Self::Asterisk => write!(f, "*"),
             Self::Bang => write!(f, "!"),
+            Self::Catch => write!(f, "catch"),
             Self::Colon => write!(f, ":"),
             Self::Comma => write!(f, ","),
+            Self::DotDot => write!(f, ".."),
+            Self::DotDotEq => write!(f, "..="),
             Self::Else => write!(f, "else"),
             Self::Equal => write!(f, "=="),
+            Self::Export => write!(f, "export"),
             Self::False => write!(f, "false"),
             Self::ForwardSlash => write!(f, "/"),
             Self::Function => write!(f, "fn"),
@@ -60,12 +74,14 @@ impl fmt::Display for Token {
             Self::HashMacro => write!(f, "hash!"),
             Self::Ident(s) => write!(f, "Identifier({s})"),
             Self::If => write!(f, "if"),
+            Self::Import => write!(f, "import"),
             Self::Int(s) => write!(f, "Int({s})"),
             Self::LBrace => write!(f, "{{"),
             Self::LParen => write!(f, "("),
             Self::LSquare => write!(f, "["),
             Self::LessThan => write!(f, "<"),
             Self::Let => write!(f, "let"),
+            Self::Macro => write!(f, "macro"),
             Self::Minus => write!(f, "-"),
             Self::NotEqual => write!(f, "!="),
             Self::Plus => write!(f, "+"),
@@ -75,7 +91,27 @@ impl fmt::Display for Token {
             Self::Return => write!(f, "return"),
             Self::Semicolon => write!(f, ";"),
             Self::Str(s) => write!(f, "Str({s})"),
+            Self::Throw => write!(f, "throw"),
             Self::True => write!(f, "true"),
+            Self::Try => write!(f, "try"),
         }
     }
 }
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Location {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Default for Location {
+    fn default() -> Self {
+        Self { line: 1, column: 1 }
+    }
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
@@ -1,10 +1,17 @@
 #[warn(clippy::all)]
 pub mod evaluator;
+pub mod interpreter;
 pub mod lexer;
 pub mod parser;
+pub mod resolver;
 
+pub use evaluator::context::{CancellationHandle, Context, OutputBuffer};
+pub use evaluator::modules::{FileSystemLoader, ModuleLoader};
+pub use evaluator::object::builtins::Capability;
 pub use evaluator::object::environment::{Environment, HeapEnvironment};
+pub use evaluator::object::native::{Arity, Native};
 pub use evaluator::object::{Object, ObjectType};
 pub use evaluator::Evaluate;
+pub use interpreter::{Interpreter, InterpreterBuilder};
 pub use lexer::Tokenize;
 pub use parser::Parse;
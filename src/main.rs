@@ -1,11 +1,60 @@
-use anyhow::Result;
 use std::io::{stdin, stdout, Write};
+use std::path::Path;
+use std::{env, fs, process};
 
-use interpret_deez::parser::error::Error as ParserError;
-use interpret_deez::Parse;
-use interpret_deez::{Environment, Evaluate, Object};
+use interpret_deez::interpreter::Error;
+use interpret_deez::{FileSystemLoader, Interpreter, Object};
 
 fn main() {
+    match env::args().nth(1) {
+        Some(path) => run_file(&path),
+        None => repl(),
+    }
+}
+
+/// Indents every line of a (possibly multiline) message.
+fn indent(message: &str) -> String {
+    message
+        .lines()
+        .map(|l| format!("    {l}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn run_file(path: &str) {
+    let input = match fs::read_to_string(path) {
+        Ok(input) => input,
+        Err(err) => {
+            eprintln!("Could not read {path}: {err}");
+            process::exit(1);
+        }
+    };
+
+    // Imports are relative to the script
+    let directory = Path::new(path).parent().unwrap_or(Path::new(""));
+    let mut interpreter = Interpreter::builder()
+        .trusted()
+        .loader(FileSystemLoader::new(directory))
+        .build();
+
+    match interpreter.eval(&input) {
+        Ok(_) => (),
+        Err(Error::Eval(e)) => {
+            eprintln!("Evaluation error:\n{}", indent(&e.to_string()));
+            process::exit(1);
+        }
+        Err(Error::Parse(e)) => {
+            eprintln!("Parsing error:\n    {e:?}");
+            process::exit(1);
+        }
+        Err(Error::Resolve(e)) => {
+            eprintln!("Resolution error:\n{}", indent(&e.to_string()));
+            process::exit(1);
+        }
+    }
+}
+
+fn repl() {
     let monkey_face_1 = r#"                __,__
        .--.  .-"     "-.  .--.
       / .. \/  .-. .-.  \/ .. \
@@ -40,7 +89,7 @@ Type `exit` to leave.
     );
 
     let mut query = String::new();
-    let env = Environment::new_heap(None);
+    let mut interpreter = Interpreter::builder().trusted().build();
 
     loop {
         print!("> ");
@@ -58,22 +107,21 @@ Type `exit` to leave.
             break;
         }
 
-        let parser = query.parser();
-        let statements_res: Result<Vec<_>, ParserError> = parser.collect();
-
-        match statements_res {
-            Ok(stmts) => match stmts.eval_return(env.clone()) {
-                Ok(Object::Null) => (),
-                Ok(x) => println!("{x}"),
-                Err(e) => {
-                    println!("{monkey_face_2}");
-                    println!("Evaluation error:\n    {e}");
-                }
-            },
-            Err(e) => {
+        match interpreter.eval(&query) {
+            Ok(Object::Null) => (),
+            Ok(x) => println!("{x}"),
+            Err(Error::Eval(e)) => {
+                println!("{monkey_face_2}");
+                println!("Evaluation error:\n{}", indent(&e.to_string()));
+            }
+            Err(Error::Parse(e)) => {
                 println!("{monkey_face_2}");
                 println!("Parsing error:\n    {e:?}");
             }
+            Err(Error::Resolve(e)) => {
+                println!("{monkey_face_2}");
+                println!("Resolution error:\n{}", indent(&e.to_string()));
+            }
         }
 
         query.truncate(0);
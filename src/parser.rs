@@ -7,7 +7,7 @@ mod test;
 
 use std::iter::Iterator;
 
-use crate::lexer::token::Token;
+use crate::lexer::token::{Location, Token};
 use crate::lexer::{Lexer, Tokenize};
 use ast::statements as stmt;
 use error::Result;
@@ -22,6 +22,7 @@ where
 pub struct Parser<'a> {
     lexer: Lexer<'a>,
     tokens: [Option<Token>; 2],
+    locations: [Location; 2],
 }
 
 pub trait Parse {
@@ -42,6 +43,7 @@ impl<'a> Parser<'a> {
         let mut p = Self {
             lexer,
             tokens: [None, None],
+            locations: [Location::default(); 2],
         };
 
         // Fill up the token buffer
@@ -54,6 +56,9 @@ impl<'a> Parser<'a> {
     fn read_token(&mut self) {
         self.tokens.swap(0, 1);
         self.tokens[1] = self.lexer.next();
+
+        self.locations.swap(0, 1);
+        self.locations[1] = self.lexer.token_location();
     }
 }
 
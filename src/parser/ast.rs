@@ -1,4 +1,5 @@
 pub mod expressions;
+pub mod modify;
 pub mod statements;
 
 use crate::lexer::token::Token;
@@ -6,6 +7,7 @@ use crate::lexer::token::Token;
 #[derive(Debug, PartialEq, PartialOrd)]
 pub enum Precedence {
     Lowest,
+    Range,
     Equals,
     LessGreater,
     Sum,
@@ -16,6 +18,7 @@ pub enum Precedence {
 impl From<&Token> for Precedence {
     fn from(token: &Token) -> Self {
         match token {
+            Token::DotDot | Token::DotDotEq => Precedence::Range,
             Token::Equal | Token::NotEqual => Precedence::Equals,
             Token::LessThan | Token::GreaterThan => Precedence::LessGreater,
             Token::Plus | Token::Minus => Precedence::Sum,
@@ -1,6 +1,8 @@
 use super::statements::Statement;
+use crate::lexer::token::Location;
 
 use core::fmt;
+use std::sync::Arc;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expression {
@@ -11,12 +13,16 @@ pub enum Expression {
     Boolean(Boolean),
     If(If),
     Function(Function),
-    Call(Call),
+    Macro(Macro),
+    /// Shared with the frames of the calls it makes
+    Call(Arc<Call>),
     Str(Str),
     Array(Array),
     Index(Index),
     Block(StmtBlock),
     Hash(Hash),
+    Range(Range),
+    Try(Try),
     Illegal,
 }
 
@@ -30,20 +36,42 @@ impl fmt::Display for Expression {
             Expression::Boolean(b) => write!(f, "{b}"),
             Expression::If(i) => write!(f, "{}", i),
             Expression::Function(func) => write!(f, "{func}"),
+            Expression::Macro(m) => write!(f, "{m}"),
             Expression::Call(c) => write!(f, "{c}"),
             Expression::Str(s) => write!(f, "{s}"),
             Expression::Array(a) => write!(f, "{a}"),
             Expression::Index(i) => write!(f, "{i}"),
             Expression::Block(b) => write!(f, "{b}"),
             Expression::Hash(h) => write!(f, "{h}"),
+            Expression::Range(r) => write!(f, "{r}"),
+            Expression::Try(t) => write!(f, "{t}"),
             Expression::Illegal => write!(f, "ILLEGAL"),
         }
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Default)]
 pub struct Identifier {
     pub value: Box<str>,
+    pub location: Location,
+    /// Set by the resolver, unresolved identifiers are looked up by name
+    pub binding: Option<Binding>,
+}
+
+/// Identifiers are equal if they have the same name, wherever they are.
+impl PartialEq for Identifier {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+/// Where the value of a resolved identifier lives at runtime.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    /// A slot of the scope `depth` levels above the current one
+    Local { depth: usize, slot: usize },
+    /// A name in the global scope, `depth` levels above the current one
+    Global { depth: usize },
 }
 
 impl fmt::Display for Identifier {
@@ -206,10 +234,37 @@ impl PartialEq for Function {
     }
 }
 
+/// Receives its arguments unevaluated and returns the code replacing the call.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Macro {
+    pub parameters: Vec<Identifier>,
+    pub body: StmtBlock,
+}
+
+impl fmt::Display for Macro {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tabbed_body = self.body.to_string().replace('\n', "\n  ");
+        tabbed_body.truncate(tabbed_body.len() - 3);
+        tabbed_body.push('}');
+
+        write!(
+            f,
+            "macro({}) {}",
+            self.parameters
+                .iter()
+                .map(|p| p.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            tabbed_body
+        )
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Call {
     pub function: Box<Expression>,
     pub arguments: Vec<Expression>,
+    pub location: Location,
 }
 
 impl fmt::Display for Call {
@@ -306,3 +361,53 @@ impl fmt::Display for Hash {
         write!(f, "}}")
     }
 }
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Range {
+    pub start: Option<Box<Expression>>,
+    pub end: Option<Box<Expression>>,
+    pub inclusive: bool,
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+
+        if let Some(start) = &self.start {
+            write!(f, "{start}")?;
+        }
+
+        write!(f, "{}", if self.inclusive { "..=" } else { ".." })?;
+
+        if let Some(end) = &self.end {
+            write!(f, "{end}")?;
+        }
+
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Try {
+    pub block: StmtBlock,
+    pub error: Identifier,
+    pub handler: StmtBlock,
+}
+
+impl fmt::Display for Try {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut tabbed_block = self.block.to_string().replace('\n', "\n  ");
+        tabbed_block.truncate(tabbed_block.len() - 3);
+        tabbed_block.push('}');
+
+        let mut tabbed_handler = self.handler.to_string().replace('\n', "\n  ");
+        tabbed_handler.truncate(tabbed_handler.len() - 3);
+        tabbed_handler.push('}');
+
+        write!(
+            f,
+            "try {} catch ({}) {}",
+            tabbed_block, self.error, tabbed_handler
+        )
+    }
+}
@@ -0,0 +1,104 @@
+use super::expressions::{Expression, StmtBlock};
+use super::statements::Statement;
+
+use std::sync::Arc;
+
+/// Rewrites a tree in place, calling the modifier on every expression after
+/// its children.
+pub trait Modify {
+    fn modify<E>(
+        &mut self,
+        modifier: &mut impl FnMut(&mut Expression) -> Result<(), E>,
+    ) -> Result<(), E>;
+}
+
+impl Modify for Expression {
+    fn modify<E>(
+        &mut self,
+        modifier: &mut impl FnMut(&mut Expression) -> Result<(), E>,
+    ) -> Result<(), E> {
+        match self {
+            Self::Prefix(p) => p.right.modify(modifier)?,
+            Self::Infix(i) => {
+                i.left.modify(modifier)?;
+                i.right.modify(modifier)?;
+            }
+            Self::If(i) => {
+                i.condition.modify(modifier)?;
+                i.consequence.modify(modifier)?;
+
+                if let Some(alternative) = &mut i.alternative {
+                    alternative.modify(modifier)?;
+                }
+            }
+            Self::Function(f) => f.body.modify(modifier)?,
+            Self::Macro(m) => m.body.modify(modifier)?,
+            Self::Call(c) => {
+                let c = Arc::make_mut(c);
+                c.function.modify(modifier)?;
+                c.arguments.modify(modifier)?;
+            }
+            Self::Array(a) => a.value.modify(modifier)?,
+            Self::Index(i) => {
+                i.left.modify(modifier)?;
+                i.index.modify(modifier)?;
+            }
+            Self::Block(b) => b.modify(modifier)?,
+            Self::Hash(h) => {
+                for (k, v) in &mut h.entries {
+                    k.modify(modifier)?;
+                    v.modify(modifier)?;
+                }
+            }
+            Self::Range(r) => {
+                for bound in [&mut r.start, &mut r.end].into_iter().flatten() {
+                    bound.modify(modifier)?;
+                }
+            }
+            Self::Try(t) => {
+                t.block.modify(modifier)?;
+                t.handler.modify(modifier)?;
+            }
+            Self::Identifier(_)
+            | Self::Integer(_)
+            | Self::Boolean(_)
+            | Self::Str(_)
+            | Self::Illegal => {}
+        }
+
+        modifier(self)
+    }
+}
+
+impl Modify for Statement {
+    fn modify<E>(
+        &mut self,
+        modifier: &mut impl FnMut(&mut Expression) -> Result<(), E>,
+    ) -> Result<(), E> {
+        match self {
+            Self::Let(l) | Self::Export(l) => l.value.modify(modifier),
+            Self::Return(r) => r.return_value.modify(modifier),
+            Self::Throw(t) => t.value.modify(modifier),
+            Self::Expression(e) => e.expression.modify(modifier),
+            Self::Import(_) | Self::EOF => Ok(()),
+        }
+    }
+}
+
+impl Modify for StmtBlock {
+    fn modify<E>(
+        &mut self,
+        modifier: &mut impl FnMut(&mut Expression) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.statements.modify(modifier)
+    }
+}
+
+impl<T: Modify> Modify for Vec<T> {
+    fn modify<E>(
+        &mut self,
+        modifier: &mut impl FnMut(&mut Expression) -> Result<(), E>,
+    ) -> Result<(), E> {
+        self.iter_mut().try_for_each(|node| node.modify(modifier))
+    }
+}
@@ -1,10 +1,14 @@
 use super::expressions::{Expression, Identifier};
+use crate::lexer::token::Location;
 use core::fmt;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Statement {
     Let(Let),
     Return(Return),
+    Throw(Throw),
+    Import(Import),
+    Export(Let),
     Expression(ExpressionStmt),
     EOF,
 }
@@ -14,6 +18,9 @@ impl fmt::Display for Statement {
         match self {
             Statement::Let(l) => write!(f, "{l}"),
             Statement::Return(r) => write!(f, "{r}"),
+            Statement::Throw(t) => write!(f, "{t}"),
+            Statement::Import(i) => write!(f, "{i}"),
+            Statement::Export(l) => write!(f, "export {l}"),
             Statement::Expression(e) => write!(f, "{e}"),
             Statement::EOF => write!(f, "EOF"),
         }
@@ -43,6 +50,31 @@ impl fmt::Display for Return {
     }
 }
 
+#[derive(Debug, Clone, PartialEq)]
+pub struct Throw {
+    pub value: Expression,
+}
+
+impl fmt::Display for Throw {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "throw {};", self.value)
+    }
+}
+
+/// Binds the exports of the module at `path` to `name`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub path: Box<str>,
+    pub name: Identifier,
+    pub location: Location,
+}
+
+impl fmt::Display for Import {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "import \"{}\" as {};", self.path, self.name)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct ExpressionStmt {
     pub expression: Expression,
@@ -3,11 +3,15 @@ use super::error::{Error, Result};
 use super::{assert_token, FromParser, Parser};
 use crate::lexer::token::Token;
 
+use std::sync::Arc;
+
 impl FromParser for expr::Identifier {
     fn parse(parser: &mut Parser, _: &Precedence) -> Result<Self> {
         match &parser.tokens[0] {
             Some(Token::Ident(value)) => Ok(expr::Identifier {
                 value: value.clone(),
+                location: parser.locations[0],
+                binding: None,
             }),
             _ => unreachable!(),
         }
@@ -16,6 +20,8 @@ impl FromParser for expr::Identifier {
 
 impl FromParser for expr::Expression {
     fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
+        let location = parser.locations[0];
+
         let first_expr = match &parser.tokens[0] {
             Some(Token::Ident(_)) => {
                 expr::Identifier::parse(parser, precedence).map(Self::Identifier)
@@ -38,10 +44,15 @@ impl FromParser for expr::Expression {
                 Ok(expr)
             }
             Some(Token::If) => expr::If::parse(parser, precedence).map(Self::If),
+            Some(Token::Try) => expr::Try::parse(parser, precedence).map(Self::Try),
             Some(Token::Function) => expr::Function::parse(parser, precedence).map(Self::Function),
+            Some(Token::Macro) => expr::Macro::parse(parser, precedence).map(Self::Macro),
             Some(Token::LSquare) => expr::Array::parse(parser, precedence).map(Self::Array),
             Some(Token::LBrace) => expr::StmtBlock::parse(parser, precedence).map(Self::Block),
             Some(Token::HashMacro) => expr::Hash::parse(parser, precedence).map(Self::Hash),
+            Some(Token::DotDot) | Some(Token::DotDotEq) => {
+                expr::Range::parse(parser, precedence).map(Self::Range)
+            }
             Some(t) => {
                 // This is a hack to avoid an infinite loop
                 let token = t.clone();
@@ -67,8 +78,20 @@ impl FromParser for expr::Expression {
                     parser.read_token();
                     let mut call = expr::Call::parse(parser, &Precedence::Lowest)?;
                     *call.function = left;
+                    call.location = location;
+
+                    left = Self::Call(Arc::new(call));
+                }
+                Some(Token::DotDot) | Some(Token::DotDotEq) => {
+                    if Precedence::Range <= *precedence {
+                        break;
+                    }
+                    parser.read_token();
 
-                    left = Self::Call(call);
+                    let mut range = expr::Range::parse(parser, &Precedence::Range)?;
+                    range.start = Some(Box::new(left));
+
+                    left = Self::Range(range);
                 }
                 Some(t) => {
                     let new_precedence: Precedence = t.into();
@@ -234,6 +257,22 @@ impl FromParser for expr::Function {
     }
 }
 
+impl FromParser for expr::Macro {
+    fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
+        assert_token(&parser.tokens[0], Token::Macro)?;
+
+        assert_token(&parser.tokens[1], Token::LParen)?;
+        parser.read_token();
+        let parameters = Vec::parse(parser, precedence)?;
+        assert_token(&parser.tokens[0], Token::RParen)?;
+        parser.read_token();
+
+        let body = expr::StmtBlock::parse(parser, precedence)?;
+
+        Ok(Self { parameters, body })
+    }
+}
+
 impl FromParser for Vec<expr::Expression> {
     fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
         let matching_token = match parser.tokens[0] {
@@ -278,6 +317,7 @@ impl FromParser for expr::Call {
         Ok(Self {
             function: Box::new(expr::Expression::Illegal),
             arguments,
+            location: parser.locations[0],
         })
     }
 }
@@ -370,3 +410,79 @@ impl FromParser for expr::Hash {
         Ok(Self { entries })
     }
 }
+
+impl FromParser for expr::Range {
+    fn parse(parser: &mut Parser, _: &Precedence) -> Result<Self> {
+        let inclusive = match parser.tokens[0] {
+            Some(Token::DotDot) => false,
+            Some(Token::DotDotEq) => true,
+            _ => unreachable!(),
+        };
+
+        // Only exclusive ranges may be left open-ended, e.g. `arr[2..]`
+        let open_ended = matches!(
+            parser.tokens[1],
+            None | Some(Token::RSquare)
+                | Some(Token::RParen)
+                | Some(Token::RBrace)
+                | Some(Token::Comma)
+                | Some(Token::Semicolon)
+        );
+
+        let end = if open_ended && !inclusive {
+            None
+        } else {
+            parser.read_token();
+            Some(Box::new(expr::Expression::parse(
+                parser,
+                &Precedence::Range,
+            )?))
+        };
+
+        Ok(Self {
+            start: None,
+            end,
+            inclusive,
+        })
+    }
+}
+
+impl FromParser for expr::Try {
+    fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
+        assert_token(&parser.tokens[0], Token::Try)?;
+        assert_token(&parser.tokens[1], Token::LBrace)?;
+        parser.read_token();
+
+        let block = expr::StmtBlock::parse(parser, precedence)?;
+
+        assert_token(&parser.tokens[1], Token::Catch)?;
+        parser.read_token();
+        assert_token(&parser.tokens[1], Token::LParen)?;
+        parser.read_token();
+
+        match &parser.tokens[1] {
+            Some(Token::Ident(_)) => parser.read_token(),
+            Some(t) => {
+                return Err(Error::UnexpectedTokenError {
+                    expected: Token::Ident("name".into()),
+                    received: t.clone(),
+                })
+            }
+            None => return Err(Error::EOFError),
+        }
+        let error = expr::Identifier::parse(parser, precedence)?;
+
+        assert_token(&parser.tokens[1], Token::RParen)?;
+        parser.read_token();
+        assert_token(&parser.tokens[1], Token::LBrace)?;
+        parser.read_token();
+
+        let handler = expr::StmtBlock::parse(parser, precedence)?;
+
+        Ok(Self {
+            block,
+            error,
+            handler,
+        })
+    }
+}
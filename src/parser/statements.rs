@@ -14,6 +14,12 @@ impl FromParser for stmt::Statement {
             None => Ok(Self::EOF),
             Some(Token::Let) => stmt::Let::parse(parser, precedence).map(Self::Let),
             Some(Token::Return) => stmt::Return::parse(parser, precedence).map(Self::Return),
+            Some(Token::Throw) => stmt::Throw::parse(parser, precedence).map(Self::Throw),
+            Some(Token::Import) => stmt::Import::parse(parser, precedence).map(Self::Import),
+            Some(Token::Export) => {
+                parser.read_token();
+                stmt::Let::parse(parser, precedence).map(Self::Export)
+            }
             Some(_) => stmt::ExpressionStmt::parse(parser, precedence).map(Self::Expression),
         };
 
@@ -60,6 +66,59 @@ impl FromParser for stmt::Return {
     }
 }
 
+impl FromParser for stmt::Throw {
+    fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
+        assert_token(&parser.tokens[0], Token::Throw)?;
+
+        parser.read_token();
+        let expression = expr::Expression::parse(parser, precedence)?;
+
+        parser.read_token();
+        Ok(Self { value: expression })
+    }
+}
+
+impl FromParser for stmt::Import {
+    fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
+        assert_token(&parser.tokens[0], Token::Import)?;
+        let location = parser.locations[0];
+
+        let path = match &parser.tokens[1] {
+            Some(Token::Str(path)) => path.clone(),
+            Some(t) => {
+                return Err(Error::UnexpectedTokenError {
+                    expected: Token::Str("path".into()),
+                    received: t.clone(),
+                })
+            }
+            None => return Err(Error::EOFError),
+        };
+        parser.read_token();
+
+        assert_token(&parser.tokens[1], Token::As)?;
+        parser.read_token();
+
+        match &parser.tokens[1] {
+            Some(Token::Ident(_)) => parser.read_token(),
+            Some(t) => {
+                return Err(Error::UnexpectedTokenError {
+                    expected: Token::Ident("name".into()),
+                    received: t.clone(),
+                })
+            }
+            None => return Err(Error::EOFError),
+        }
+        let name = expr::Identifier::parse(parser, precedence)?;
+
+        parser.read_token();
+        Ok(Self {
+            path,
+            name,
+            location,
+        })
+    }
+}
+
 impl FromParser for stmt::ExpressionStmt {
     fn parse(parser: &mut Parser, precedence: &Precedence) -> Result<Self> {
         if parser.tokens[0].is_none() {
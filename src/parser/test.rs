@@ -1,6 +1,10 @@
+#![allow(clippy::explicit_auto_deref, clippy::needless_borrow, clippy::ptr_arg)]
+
 use std::fmt::Debug;
 
 use super::ast::{expressions as expr, statements as stmt};
+use super::error::Error;
+use crate::lexer::token::Token;
 use crate::parser::Parse;
 
 struct PrefixTest {
@@ -482,6 +486,30 @@ fn test_operator_precedence() {
             input: "add(a + b + c * d / f + g)",
             expected: "add((((a + b) + ((c * d) / f)) + g));",
         },
+        ParserOutputTest {
+            input: "1..n + 1",
+            expected: "(1..(n + 1));",
+        },
+        ParserOutputTest {
+            input: "a - 1..=b * 2",
+            expected: "((a - 1)..=(b * 2));",
+        },
+        ParserOutputTest {
+            input: "arr[1..3]",
+            expected: "arr[(1..3)];",
+        },
+        ParserOutputTest {
+            input: "arr[..-1]",
+            expected: "arr[(..(-1))];",
+        },
+        ParserOutputTest {
+            input: "s[2..]",
+            expected: "s[(2..)];",
+        },
+        ParserOutputTest {
+            input: "f(.., 1..)",
+            expected: "f((..), (1..));",
+        },
     ];
 
     for test in tests {
@@ -783,7 +811,7 @@ fn test_call_expr() {
             Ok(s) => match s {
                 stmt::Statement::Expression(expr_stmt) => match expr_stmt.expression {
                     expr::Expression::Call(c) => {
-                        match *c.function {
+                        match &*c.function {
                             expr::Expression::Identifier(i) => test_ident(&i, "add"),
                             _ => panic!("Wrong type of function received"),
                         }
@@ -876,6 +904,125 @@ fn test_array_expr() {
     }
 }
 
+#[test]
+fn test_try_expr() {
+    let tests = vec![
+        ParserOutputTest {
+            input: "try { x } catch (e) { y }",
+            expected: "try {\n  x;\n} catch (e) {\n  y;\n};",
+        },
+        ParserOutputTest {
+            input: "throw x + 1",
+            expected: "throw (x + 1);",
+        },
+        ParserOutputTest {
+            input: "let a = try { throw error(\"Kind\", \"msg\") } catch (err) { err };",
+            expected:
+                "let a = try {\n  throw error(\"Kind\", \"msg\");\n} catch (err) {\n  err;\n};",
+        },
+    ];
+
+    for test in tests {
+        let parser = test.input.parser();
+
+        let statements: Vec<_> = parser.collect();
+
+        let length = statements.len();
+        if length != 1 {
+            for stmt in statements {
+                match stmt {
+                    Ok(s) => println!("{}", s),
+                    Err(err) => println!("Error: {}", err),
+                }
+            }
+            panic!("Expected 1 statement, got {}", length);
+        }
+
+        assert_eq!(test.expected, statements[0].as_ref().unwrap().to_string());
+    }
+
+    let result: Result<Vec<_>, _> = "try { x } catch (1) { y }".parser().collect();
+    assert_eq!(
+        result.unwrap_err(),
+        Error::UnexpectedTokenError {
+            expected: Token::Ident("name".into()),
+            received: Token::Int("1".into()),
+        }
+    );
+}
+
+#[test]
+fn test_macro_expr() {
+    let tests = vec![
+        ParserOutputTest {
+            input: "macro(x, y) { x + y; }",
+            expected: "macro(x, y) {\n  (x + y);\n};",
+        },
+        ParserOutputTest {
+            input: "let unless = macro(cond) { quote(!unquote(cond)) };",
+            expected: "let unless = macro(cond) {\n  quote((!unquote(cond)));\n};",
+        },
+    ];
+
+    for test in tests {
+        let statements: Vec<_> = test.input.parser().collect();
+
+        assert_eq!(statements.len(), 1, "Failed input: {}", test.input);
+        assert_eq!(test.expected, statements[0].as_ref().unwrap().to_string());
+    }
+}
+
+#[test]
+fn test_modules() {
+    let tests = vec![
+        ParserOutputTest {
+            input: r#"import "lib/math.mk" as math"#,
+            expected: r#"import "lib/math.mk" as math;"#,
+        },
+        ParserOutputTest {
+            input: "export let add = fn(a, b) { a + b };",
+            expected: "export let add = fn(a, b) {\n  (a + b);\n};",
+        },
+    ];
+
+    for test in tests {
+        let statements: Vec<_> = test.input.parser().collect();
+
+        assert_eq!(statements.len(), 1, "Failed input: {}", test.input);
+        assert_eq!(test.expected, statements[0].as_ref().unwrap().to_string());
+    }
+
+    let errors = vec![
+        (
+            "import math as math",
+            Error::UnexpectedTokenError {
+                expected: Token::Str("path".into()),
+                received: Token::Ident("math".into()),
+            },
+        ),
+        (
+            r#"import "math.mk" math"#,
+            Error::UnexpectedTokenError {
+                expected: Token::As,
+                received: Token::Ident("math".into()),
+            },
+        ),
+        (
+            "export fn() {}",
+            Error::UnexpectedTokenError {
+                expected: Token::Let,
+                received: Token::Function,
+            },
+        ),
+    ];
+
+    for (input, expected) in errors {
+        let result: Result<Vec<_>, _> = input.parser().collect();
+
+        assert_eq!(result.unwrap_err(), expected, "Failed input: {input}");
+    }
+}
+
 #[test]
 fn test_hash_expr() {
     let tests = vec![
@@ -896,6 +1043,7 @@ fn test_hash_expr() {
                 (
                     expr::Expression::Identifier(expr::Identifier {
                         value: "joe".into(),
+                        ..Default::default()
                     }),
                     expr::Expression::Boolean(expr::Boolean { value: true }),
                 ),
@@ -903,6 +1051,7 @@ fn test_hash_expr() {
                     expr::Expression::Boolean(expr::Boolean { value: true }),
                     expr::Expression::Identifier(expr::Identifier {
                         value: "joe".into(),
+                        ..Default::default()
                     }),
                 ),
             ],
@@ -0,0 +1,331 @@
+#[cfg(test)]
+mod test;
+
+use crate::evaluator::object::environment::HeapEnvironment;
+use crate::lexer::token::Location;
+use crate::parser::ast::expressions::{self as expr, Binding, Expression};
+use crate::parser::ast::modify::Modify;
+use crate::parser::ast::statements::Statement;
+
+use core::convert::Infallible;
+use core::fmt;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq)]
+pub enum Error {
+    #[error("Undefined variable {name} at {location}")]
+    Undefined { name: String, location: Location },
+    #[error("Variable {name} used before its definition at {location}")]
+    UsedBeforeDefinition { name: String, location: Location },
+    #[error("Export of {name} at {location} isn't at the top level")]
+    NestedExport { name: String, location: Location },
+    #[error("Local variable {name} can't be deleted at {location}")]
+    DeletedLocal { name: String, location: Location },
+}
+
+/// Every problem found in a program.
+#[derive(Error, Debug, Clone, PartialEq)]
+pub struct Diagnostics(pub Vec<Error>);
+
+impl fmt::Display for Diagnostics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let errors: Vec<_> = self.0.iter().map(|e| e.to_string()).collect();
+
+        write!(f, "{}", errors.join("\n"))
+    }
+}
+
+/// Annotates every identifier in `program` with where its value lives at
+/// runtime, `env` being the global environment it will be evaluated in.
+///
+/// The program is fully annotated even if problems are found, identifiers
+/// that can't be resolved are left to be looked up by name.
+pub fn resolve(program: &mut [Statement], env: &HeapEnvironment) -> Result<(), Diagnostics> {
+    let env = env.borrow();
+    let mut resolver = Resolver::new(|name| env.contains(name));
+
+    resolver.program(program);
+
+    match resolver.errors.is_empty() {
+        true => Ok(()),
+        false => Err(Diagnostics(resolver.errors)),
+    }
+}
+
+/// Names declared in a runtime environment other than the global one.
+struct Scope {
+    /// Slot of every name and whether it has been defined yet
+    names: HashMap<Box<str>, (usize, bool)>,
+    /// Functions enclosing the scope
+    function: usize,
+}
+
+struct Resolver<F> {
+    scopes: Vec<Scope>,
+    /// Names declared at the top level of the program
+    globals: HashMap<Box<str>, bool>,
+    is_global: F,
+    function: usize,
+    errors: Vec<Error>,
+}
+
+impl<F> Resolver<F>
+where
+    F: Fn(&str) -> bool,
+{
+    fn new(is_global: F) -> Self {
+        Self {
+            scopes: Vec::new(),
+            globals: HashMap::new(),
+            is_global,
+            function: 0,
+            errors: Vec::new(),
+        }
+    }
+
+    fn program(&mut self, program: &mut [Statement]) {
+        for name in declarations(program) {
+            let defined = (self.is_global)(name);
+            self.globals.entry(name.into()).or_insert(defined);
+        }
+
+        self.statements(program);
+    }
+
+    /// Starts a scope where every `let` in `statements` is visible from the
+    /// beginning, so uses before the definition can be told apart from outer
+    /// names.
+    fn push_scope(&mut self, parameters: &mut [expr::Identifier], statements: &[Statement]) {
+        self.scopes.push(Scope {
+            names: HashMap::new(),
+            function: self.function,
+        });
+
+        for parameter in parameters {
+            self.declare(parameter, true);
+        }
+        for name in declarations(statements) {
+            let scope = self.scopes.last_mut().expect("a scope was just pushed");
+            let slot = scope.names.len();
+
+            scope.names.entry(name.into()).or_insert((slot, false));
+        }
+    }
+
+    fn declare(&mut self, name: &mut expr::Identifier, defined: bool) {
+        let Some(scope) = self.scopes.last_mut() else {
+            self.globals.insert(name.value.clone(), defined);
+            name.binding = Some(Binding::Global { depth: 0 });
+            return;
+        };
+
+        let slot = scope.names.len();
+        let (slot, is_defined) = scope
+            .names
+            .entry(name.value.clone())
+            .or_insert((slot, false));
+        *is_defined |= defined;
+
+        name.binding = Some(Binding::Local {
+            depth: 0,
+            slot: *slot,
+        });
+    }
+
+    fn lookup(&mut self, name: &mut expr::Identifier) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if let Some(&(slot, defined)) = scope.names.get(&name.value) {
+                // Functions defined earlier may run once the name is defined
+                if !defined && scope.function == self.function {
+                    self.used_before_definition(name);
+                }
+
+                name.binding = Some(Binding::Local { depth, slot });
+                return;
+            }
+        }
+
+        let depth = self.scopes.len();
+
+        match self.globals.get(&name.value) {
+            Some(&defined) => {
+                if !defined && self.function == 0 {
+                    self.used_before_definition(name);
+                }
+
+                name.binding = Some(Binding::Global { depth });
+            }
+            None if (self.is_global)(&name.value) => name.binding = Some(Binding::Global { depth }),
+            None => self.errors.push(Error::Undefined {
+                name: name.value.to_string(),
+                location: name.location,
+            }),
+        }
+    }
+
+    fn used_before_definition(&mut self, name: &expr::Identifier) {
+        self.errors.push(Error::UsedBeforeDefinition {
+            name: name.value.to_string(),
+            location: name.location,
+        });
+    }
+
+    /// `del` removes names from an environment's store, locals live in slots
+    /// instead. Deleting one fails at runtime, literal names are caught early.
+    fn check_del(&mut self, call: &expr::Call) {
+        let (Expression::Identifier(function), [Expression::Str(name)]) =
+            (call.function.as_ref(), call.arguments.as_slice())
+        else {
+            return;
+        };
+
+        let is_del =
+            &*function.value == "del" && matches!(function.binding, Some(Binding::Global { .. }));
+
+        if is_del
+            && self
+                .scopes
+                .iter()
+                .any(|s| s.names.contains_key(&name.value))
+        {
+            self.errors.push(Error::DeletedLocal {
+                name: name.value.to_string(),
+                location: call.location,
+            });
+        }
+    }
+
+    fn statements(&mut self, statements: &mut [Statement]) {
+        for statement in statements {
+            self.statement(statement);
+        }
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Let(l) => {
+                self.expression(&mut l.value);
+                self.declare(&mut l.name, true);
+            }
+            Statement::Export(l) => {
+                if !self.scopes.is_empty() {
+                    self.errors.push(Error::NestedExport {
+                        name: l.name.value.to_string(),
+                        location: l.name.location,
+                    });
+                }
+
+                self.expression(&mut l.value);
+                self.declare(&mut l.name, true);
+            }
+            Statement::Import(i) => self.declare(&mut i.name, true),
+            Statement::Return(r) => self.expression(&mut r.return_value),
+            Statement::Throw(t) => self.expression(&mut t.value),
+            Statement::Expression(e) => self.expression(&mut e.expression),
+            Statement::EOF => {}
+        }
+    }
+
+    fn block(&mut self, block: &mut expr::StmtBlock) {
+        self.push_scope(&mut [], &block.statements);
+        self.statements(&mut block.statements);
+        self.scopes.pop();
+    }
+
+    fn expression(&mut self, expression: &mut Expression) {
+        match expression {
+            Expression::Identifier(i) => self.lookup(i),
+            Expression::Prefix(p) => self.expression(&mut p.right),
+            Expression::Infix(i) => {
+                self.expression(&mut i.left);
+                self.expression(&mut i.right);
+            }
+            Expression::If(i) => {
+                self.expression(&mut i.condition);
+                self.block(&mut i.consequence);
+
+                if let Some(alternative) = &mut i.alternative {
+                    self.block(alternative);
+                }
+            }
+            Expression::Function(f) => {
+                self.function += 1;
+                // Parameters and the body share a scope
+                self.push_scope(&mut f.parameters, &f.body.statements);
+                self.statements(&mut f.body.statements);
+                self.scopes.pop();
+                self.function -= 1;
+            }
+            // Quoted code is resolved where it ends up, except for what's
+            // evaluated when quoting
+            Expression::Call(c) if c.is_quote() => {
+                let Ok(()) = Arc::make_mut(c).arguments.modify(&mut |e| {
+                    if let Expression::Call(c) = e {
+                        if c.is_unquote() {
+                            let c = Arc::make_mut(c);
+                            c.arguments.iter_mut().for_each(|a| self.expression(a));
+                        }
+                    }
+
+                    Ok::<_, Infallible>(())
+                });
+            }
+            Expression::Call(c) => {
+                let c = Arc::make_mut(c);
+                self.expression(&mut c.function);
+
+                for argument in &mut c.arguments {
+                    self.expression(argument);
+                }
+
+                self.check_del(c);
+            }
+            Expression::Array(a) => {
+                for element in &mut a.value {
+                    self.expression(element);
+                }
+            }
+            Expression::Index(i) => {
+                self.expression(&mut i.left);
+                self.expression(&mut i.index);
+            }
+            Expression::Block(b) => self.block(b),
+            Expression::Hash(h) => {
+                for (k, v) in &mut h.entries {
+                    self.expression(k);
+                    self.expression(v);
+                }
+            }
+            Expression::Range(r) => {
+                for bound in [&mut r.start, &mut r.end].into_iter().flatten() {
+                    self.expression(bound);
+                }
+            }
+            Expression::Try(t) => {
+                self.block(&mut t.block);
+                // The error binding and the handler share a scope
+                self.push_scope(std::slice::from_mut(&mut t.error), &t.handler.statements);
+                self.statements(&mut t.handler.statements);
+                self.scopes.pop();
+            }
+            // Macros run before resolution, looking names up as they go
+            Expression::Macro(_) => {}
+            Expression::Integer(_)
+            | Expression::Boolean(_)
+            | Expression::Str(_)
+            | Expression::Illegal => {}
+        }
+    }
+}
+
+/// Names declared directly in `statements`.
+fn declarations(statements: &[Statement]) -> impl Iterator<Item = &str> {
+    statements.iter().filter_map(|s| match s {
+        Statement::Let(l) | Statement::Export(l) => Some(l.name.value.as_ref()),
+        Statement::Import(i) => Some(i.name.value.as_ref()),
+        _ => None,
+    })
+}
@@ -0,0 +1,205 @@
+use super::{resolve, Diagnostics, Error};
+use crate::evaluator::object::builtins::Builtin;
+use crate::evaluator::object::environment::Environment;
+use crate::evaluator::object::Object;
+use crate::lexer::token::Location;
+use crate::parser::ast::expressions::{Binding, Expression};
+use crate::parser::ast::statements::Statement;
+use crate::parser::Parse;
+
+struct ResolveErrorTest {
+    pub input: &'static str,
+    pub expected: Vec<Error>,
+}
+
+fn resolve_input(input: &str) -> (Vec<Statement>, Result<(), Diagnostics>) {
+    let mut stmts: Vec<_> = input.parser().collect::<Result<_, _>>().unwrap();
+    let env = Environment::new_heap(None);
+    env.borrow_mut().set("known", Object::Integer(1));
+    env.borrow_mut().set("del", Object::Builtin(Builtin::Del));
+
+    let result = resolve(&mut stmts, &env);
+
+    (stmts, result)
+}
+
+#[test]
+fn test_resolve_bindings() {
+    let (stmts, result) = resolve_input("let a = 1; fn(x) { let y = x; fn() { a + y } }");
+
+    assert_eq!(result, Ok(()));
+
+    let Statement::Let(a) = &stmts[0] else {
+        panic!("Let expected, received {}", stmts[0]);
+    };
+    assert_eq!(a.name.binding, Some(Binding::Global { depth: 0 }));
+
+    let Statement::Expression(outer) = &stmts[1] else {
+        panic!("Expression expected, received {}", stmts[1]);
+    };
+    let Expression::Function(outer) = &outer.expression else {
+        panic!("Function expected, received {outer}");
+    };
+    assert_eq!(
+        outer.parameters[0].binding,
+        Some(Binding::Local { depth: 0, slot: 0 })
+    );
+
+    let Statement::Let(y) = &outer.body.statements[0] else {
+        panic!("Let expected, received {}", outer.body.statements[0]);
+    };
+    assert_eq!(y.name.binding, Some(Binding::Local { depth: 0, slot: 1 }));
+    let Expression::Identifier(x) = &y.value else {
+        panic!("Identifier expected, received {}", y.value);
+    };
+    assert_eq!(x.binding, Some(Binding::Local { depth: 0, slot: 0 }));
+
+    let Statement::Expression(inner) = &outer.body.statements[1] else {
+        panic!("Expression expected, received {}", outer.body.statements[1]);
+    };
+    let Expression::Function(inner) = &inner.expression else {
+        panic!("Function expected, received {inner}");
+    };
+    let Statement::Expression(sum) = &inner.body.statements[0] else {
+        panic!("Expression expected, received {}", inner.body.statements[0]);
+    };
+    let Expression::Infix(sum) = &sum.expression else {
+        panic!("Infix expected, received {sum}");
+    };
+
+    match (sum.left.as_ref(), sum.right.as_ref()) {
+        (Expression::Identifier(a), Expression::Identifier(y)) => {
+            assert_eq!(a.binding, Some(Binding::Global { depth: 2 }));
+            assert_eq!(y.binding, Some(Binding::Local { depth: 1, slot: 1 }));
+        }
+        x => panic!("Identifiers expected, received {x:?}"),
+    }
+}
+
+#[test]
+fn test_resolve_valid() {
+    let tests = vec![
+        "known + len([])",
+        "let f = fn() { g() }; let g = fn() { 1 }; f()",
+        "fn() { let even = fn(n) { odd(n) }; let odd = fn(n) { even(n) }; even }",
+        "let a = 1; let a = a + 1;",
+        "fn(a) { let a = a + 1; a }",
+        "{ let a = 1; { a } }",
+        "try { 1 } catch (e) { let b = e; b }",
+        "let known = known + 1;",
+        r#"let a = 1; fn() { del("a") }"#,
+        r#"fn() { let del = fn(x) { x }; let a = 1; del("a") }"#,
+    ];
+
+    for input in tests {
+        let (_, result) = resolve_input(input);
+
+        assert_eq!(result, Ok(()), "Failed input: {input}");
+    }
+}
+
+#[test]
+fn test_resolve_errors() {
+    let tests = vec![
+        ResolveErrorTest {
+            input: "joe",
+            expected: vec![Error::Undefined {
+                name: "joe".into(),
+                location: Location { line: 1, column: 1 },
+            }],
+        },
+        ResolveErrorTest {
+            input: "fn() { joe }; { mama }",
+            expected: vec![
+                Error::Undefined {
+                    name: "joe".into(),
+                    location: Location { line: 1, column: 8 },
+                },
+                Error::Undefined {
+                    name: "mama".into(),
+                    location: Location {
+                        line: 1,
+                        column: 17,
+                    },
+                },
+            ],
+        },
+        ResolveErrorTest {
+            input: "a; let a = 1;",
+            expected: vec![Error::UsedBeforeDefinition {
+                name: "a".into(),
+                location: Location { line: 1, column: 1 },
+            }],
+        },
+        ResolveErrorTest {
+            input: "fn() {\n  let b = a;\n  let a = 1;\n}",
+            expected: vec![Error::UsedBeforeDefinition {
+                name: "a".into(),
+                location: Location {
+                    line: 2,
+                    column: 11,
+                },
+            }],
+        },
+        ResolveErrorTest {
+            input: "fn() { let a = a; }",
+            expected: vec![Error::UsedBeforeDefinition {
+                name: "a".into(),
+                location: Location {
+                    line: 1,
+                    column: 16,
+                },
+            }],
+        },
+        ResolveErrorTest {
+            input: "try { 1 } catch (e) { 2 }; e",
+            expected: vec![Error::Undefined {
+                name: "e".into(),
+                location: Location {
+                    line: 1,
+                    column: 28,
+                },
+            }],
+        },
+        ResolveErrorTest {
+            input: r#"fn(a) { del("a") }"#,
+            expected: vec![Error::DeletedLocal {
+                name: "a".into(),
+                location: Location { line: 1, column: 9 },
+            }],
+        },
+        ResolveErrorTest {
+            input: r#"{ let b = 1; del("b") }"#,
+            expected: vec![Error::DeletedLocal {
+                name: "b".into(),
+                location: Location {
+                    line: 1,
+                    column: 14,
+                },
+            }],
+        },
+    ];
+
+    for test in tests {
+        let (_, result) = resolve_input(test.input);
+
+        assert_eq!(
+            result,
+            Err(Diagnostics(test.expected)),
+            "Failed input: {}",
+            test.input
+        );
+    }
+}
+
+#[test]
+fn test_resolve_diagnostics_display() {
+    let (_, result) = resolve_input("a;\nb");
+
+    assert_eq!(
+        result.map_err(|e| e.to_string()),
+        Err("Undefined variable a at line 1, column 1\n\
+             Undefined variable b at line 2, column 1"
+            .into())
+    );
+}